@@ -6,6 +6,12 @@ fn analyze_benchmark(c: &mut Criterion) {
     c.bench_function("analyze small project", |b| {
         b.iter(|| analyzer.analyze_project("."))
     });
+
+    let mut analyzer = ProjectAnalyzer::new();
+    analyzer.set_threads(1);
+    c.bench_function("analyze small project, one thread", |b| {
+        b.iter(|| analyzer.analyze_project("."))
+    });
 }
 
 criterion_group!(benches, analyze_benchmark);
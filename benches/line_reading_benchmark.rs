@@ -0,0 +1,53 @@
+use codstts::core::LineReader;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A SQL dump of about 40 MB, written once to the temporary directory.
+fn sql_dump() -> PathBuf {
+    let path = std::env::temp_dir().join("codstts-bench-dump.sql");
+    if !path.exists() {
+        let mut dump = String::new();
+        for i in 0..400_000 {
+            dump.push_str(&format!(
+                "INSERT INTO users VALUES ({}, 'user{}', 'user{}@example.com'); -- row {}\n",
+                i, i, i, i
+            ));
+        }
+        fs::write(&path, dump).expect("temporary directory is writable");
+    }
+    path
+}
+
+/// Total and longest line length, reading the whole file first.
+fn read_whole(path: &Path) -> (usize, usize) {
+    let content = fs::read_to_string(path).unwrap();
+    content
+        .split_inclusive('\n')
+        .fold((0, 0), |(total, longest), line| {
+            (total + line.len(), longest.max(line.len()))
+        })
+}
+
+/// Total and longest line length, streaming the file.
+fn read_streaming(path: &Path) -> (usize, usize) {
+    let mut reader = LineReader::open(path).unwrap();
+    let (mut total, mut longest) = (0, 0);
+    while let Some(line) = reader.next_line().unwrap() {
+        total += line.len();
+        longest = longest.max(line.len());
+    }
+    (total, longest)
+}
+
+fn line_reading_benchmark(c: &mut Criterion) {
+    let path = sql_dump();
+    let mut group = c.benchmark_group("read SQL dump");
+    group.sample_size(10);
+    group.bench_function("read_to_string", |b| b.iter(|| read_whole(&path)));
+    group.bench_function("LineReader", |b| b.iter(|| read_streaming(&path)));
+    group.finish();
+}
+
+criterion_group!(benches, line_reading_benchmark);
+criterion_main!(benches);
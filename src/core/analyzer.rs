@@ -1,17 +1,129 @@
-use super::config::Config;
+use super::cache::{Cache, Entry, Stamp, CACHE_DIR};
+use super::config::{Config, EmbeddedLanguages, FileHandling, MinifiedThresholds, MixedLines};
 use super::detector::LanguageDetector;
+use super::embedded::RegionSplitter;
+use super::encoding::{Fallback, LineReader};
 use super::error::{CodeStatsError, Result};
-use super::stats::{FileStats, LanguageStats, LineStats};
-use ignore::Walk;
+use super::gitattributes::LinguistAttributes;
+use super::lexer::LineCounter;
+use super::registry::Registry;
+use super::stats::{FileRecord, FileStats, LanguageStats};
+use ignore::{ParallelVisitor, ParallelVisitorBuilder, WalkBuilder, WalkState};
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
 use std::{
-    fs,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
+/// Why a file is kept apart from the project's own code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum SetAside {
+    Vendored,
+    Generated,
+    Minified,
+}
+
+impl SetAside {
+    fn reason(self) -> &'static str {
+        match self {
+            SetAside::Vendored => "vendored",
+            SetAside::Generated => "generated",
+            SetAside::Minified => "minified",
+        }
+    }
+}
+
+/// What became of a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum Outcome {
+    /// Left out without being tallied, like lock files
+    Ignored,
+    /// Left out and tallied by reason: `binary` or a category
+    Skipped(String),
+    /// Vendored, generated or minified; `stats` are only gathered when the
+    /// file is reported or had to be read to tell it is minified
+    SetAside {
+        kind: SetAside,
+        language: String,
+        stats: Option<FileStats>,
+    },
+    Counted {
+        language: String,
+        stats: FileStats,
+    },
+}
+
+impl Outcome {
+    /// Whether the file was read in full, so caching the outcome saves work.
+    fn was_read(&self) -> bool {
+        matches!(
+            self,
+            Outcome::Counted { .. } | Outcome::SetAside { stats: Some(_), .. }
+        )
+    }
+}
+
+fn file_record(
+    path: &Path,
+    language: &str,
+    stats: &FileStats,
+    set_aside: Option<SetAside>,
+) -> FileRecord {
+    FileRecord {
+        path: path.to_path_buf(),
+        language: language.to_string(),
+        stats: stats.clone(),
+        vendored: set_aside == Some(SetAside::Vendored),
+        generated: set_aside == Some(SetAside::Generated),
+        minified: set_aside == Some(SetAside::Minified),
+    }
+}
+
+/// The configured colour of every language in `stats`.
+fn language_colors(stats: &LanguageStats, registry: &Registry) -> HashMap<String, String> {
+    let files = stats
+        .files
+        .iter()
+        .flat_map(|file| std::iter::once(&file.language).chain(file.stats.embedded.keys()));
+    stats
+        .stats
+        .keys()
+        .chain(stats.vendored.keys())
+        .chain(stats.generated.keys())
+        .chain(files)
+        .filter_map(|name| Some((name.clone(), registry.color(name)?.to_string())))
+        .collect()
+}
+
+/// What the walk found in one part of the project.
+#[derive(Default)]
+struct Findings {
+    stats: LanguageStats,
+    other_files: Vec<PathBuf>,
+    cache_entries: Vec<(String, Entry)>,
+}
+
+impl Findings {
+    fn merge(&mut self, other: Findings) {
+        self.stats.merge(other.stats);
+        self.other_files.extend(other.other_files);
+        self.cache_entries.extend(other.cache_entries);
+    }
+}
+
 pub struct ProjectAnalyzer {
     detector: LanguageDetector,
     config: Option<Config>,
+    fallback: Fallback,
+    threads: usize,
+}
+
+impl Default for ProjectAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProjectAnalyzer {
@@ -19,6 +131,8 @@ impl ProjectAnalyzer {
         Self {
             detector: LanguageDetector::new(),
             config: None,
+            fallback: Fallback::default(),
+            threads: 0,
         }
     }
 
@@ -26,27 +140,69 @@ impl ProjectAnalyzer {
         self.config = Some(config);
     }
 
+    /// Number of threads walking and analysing files; 0, the default,
+    /// picks one per CPU.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
     pub fn analyze_project(&mut self, path: &str) -> Result<(LanguageStats, Vec<PathBuf>)> {
-        let mut stats = LanguageStats::new();
-        let mut other_files = Vec::new();
-        self.detector.load_overrides(Path::new(path))?;
+        let registry = match &self.config {
+            Some(config) => {
+                self.fallback = match &config.fallback_encoding {
+                    Some(label) => Fallback::from_label(label)?,
+                    None => Fallback::default(),
+                };
+                Registry::configured(config)?
+            }
+            None => Arc::clone(Registry::builtin()),
+        };
+        self.detector
+            .load_overrides(Path::new(path), Arc::clone(&registry))?;
+
+        let cache = self
+            .config
+            .as_ref()
+            .filter(|config| config.cache)
+            .map(|config| Cache::load(Path::new(path), config));
 
         let pb = self.create_progress_bar();
 
-        for entry in Walk::new(path) {
-            let entry = match entry {
-                Ok(entry) => entry,
-                Err(e) => {
-                    eprintln!("Error accessing entry: {}", e);
-                    continue;
-                }
-            };
+        let totals = Totals {
+            findings: Mutex::new(Findings::default()),
+            error: Mutex::new(None),
+        };
+        WalkBuilder::new(path)
+            .threads(self.threads)
+            .filter_entry(|entry| entry.file_name() != CACHE_DIR)
+            .build_parallel()
+            .visit(&mut WorkerBuilder {
+                analyzer: self,
+                cache: cache.as_ref(),
+                totals: &totals,
+            });
 
-            self.process_entry(entry, &mut stats, &mut other_files)?;
+        pb.finish_and_clear();
+        if let Some(e) = totals.error.into_inner().unwrap_or_else(|e| e.into_inner()) {
+            return Err(e);
         }
+        let mut findings = totals
+            .findings
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner());
 
-        pb.finish_and_clear();
-        Ok((stats, other_files))
+        if let Some(cache) = cache {
+            if let Err(e) = cache.save(findings.cache_entries) {
+                eprintln!("Error writing the cache: {}", e);
+            }
+        }
+
+        // Threads finish in any order
+        findings.stats.files.sort_by(|a, b| a.path.cmp(&b.path));
+        findings.stats.minified.sort();
+        findings.other_files.sort();
+        findings.stats.colors = language_colors(&findings.stats, &registry);
+        Ok((findings.stats, findings.other_files))
     }
 
     fn create_progress_bar(&self) -> ProgressBar {
@@ -63,8 +219,8 @@ impl ProjectAnalyzer {
     fn process_entry(
         &self,
         entry: ignore::DirEntry,
-        stats: &mut LanguageStats,
-        other_files: &mut Vec<PathBuf>,
+        cache: Option<&Cache>,
+        findings: &mut Findings,
     ) -> Result<()> {
         let path = entry.path();
 
@@ -85,63 +241,523 @@ impl ProjectAnalyzer {
             }
         }
 
-        if !entry.file_type().map_or(false, |ft| ft.is_file()) || self.detector.should_ignore(path)
-        {
+        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+            return Ok(());
+        }
+        let attributes = self.detector.attributes(path);
+
+        // A file that cannot be stamped is analysed as if uncached
+        let Some(mut stamp) = cache.and_then(|cache| cache.stamp(path, &attributes)) else {
+            if let Some(outcome) = self.examine(path, &attributes, None) {
+                self.record(path, outcome, findings);
+            }
             return Ok(());
+        };
+        let cached = cache.and_then(|cache| cache.lookup(path, &mut stamp));
+        let outcome = match cached {
+            Some(outcome) => outcome,
+            None => match self.examine(path, &attributes, Some(&mut stamp)) {
+                Some(outcome) => outcome,
+                None => return Ok(()),
+            },
+        };
+        if outcome.was_read() {
+            findings
+                .cache_entries
+                .extend(stamp.entry(path, outcome.clone()));
+        }
+        self.record(path, outcome, findings);
+        Ok(())
+    }
+
+    /// Decides what becomes of a file, reading it if it has to be counted;
+    /// `None` if it could not be read. The hash of a file that is read is
+    /// recorded in `stamp`.
+    fn examine(
+        &self,
+        path: &Path,
+        attributes: &LinguistAttributes,
+        mut stamp: Option<&mut Stamp>,
+    ) -> Option<Outcome> {
+        if self.detector.should_ignore(path, attributes) {
+            return Some(Outcome::Ignored);
+        }
+        if self.detector.is_binary(path, attributes) {
+            return Some(Outcome::Skipped("binary".to_string()));
         }
 
         // Pass config to detect_language
-        match self.detector.detect_language(path, self.config.as_ref()) {
-            Ok(language) => {
-                if let Ok(file_stats) = self.analyze_file(path) {
-                    if language == "Other" {
-                        other_files.push(path.to_path_buf());
+        let language = match self
+            .detector
+            .detect_language(path, attributes, self.config.as_ref())
+        {
+            Ok(detection) => {
+                if detection.by_siblings {
+                    if let Some(stamp) = stamp.as_deref_mut() {
+                        stamp.depends_on_siblings();
                     }
-                    stats.update(language, file_stats);
                 }
+                detection.language
             }
-            Err(e) => eprintln!("Error detecting language for {}: {}", path.display(), e),
+            Err(e) => {
+                eprintln!("Error detecting language for {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        // linguist-detectable overrides the category filter either way
+        let category = self.detector.registry().category(language);
+        let included = match (attributes.detectable, &self.config) {
+            (Some(false), _) => return Some(Outcome::Skipped("undetectable".to_string())),
+            (Some(true), _) => true,
+            (None, Some(config)) => config.includes_category(category),
+            (None, None) => Config::default().includes_category(category),
+        };
+        if !included {
+            return Some(Outcome::Skipped(category.as_str().to_string()));
         }
 
-        Ok(())
+        let set_aside = if self.detector.is_vendored(path, attributes) {
+            Some(SetAside::Vendored)
+        } else if self.detector.is_generated(path, attributes) {
+            Some(SetAside::Generated)
+        } else {
+            None
+        };
+        if let Some(kind) = set_aside {
+            // Excluded files are not even read
+            let stats = match self.handling(kind) {
+                FileHandling::Exclude => None,
+                FileHandling::Report => Some(self.read_file(path, language, stamp)?.0),
+            };
+            return Some(Outcome::SetAside {
+                kind,
+                language: language.to_string(),
+                stats,
+            });
+        }
+
+        let (stats, minified) = self.read_file(path, language, stamp)?;
+        let language = language.to_string();
+        Some(if minified {
+            Outcome::SetAside {
+                kind: SetAside::Minified,
+                language,
+                stats: Some(stats),
+            }
+        } else {
+            Outcome::Counted { language, stats }
+        })
     }
 
-    fn analyze_file(&self, path: &Path) -> Result<FileStats> {
-        let content = fs::read_to_string(path).map_err(CodeStatsError::Io)?;
-        let mut stats = FileStats {
-            bytes: content.len(),
-            lines: LineStats::default(),
-        };
+    /// Analyses a file, reporting why if it cannot be read.
+    fn read_file(
+        &self,
+        path: &Path,
+        language: &str,
+        stamp: Option<&mut Stamp>,
+    ) -> Option<(FileStats, bool)> {
+        self.analyze_file(path, language, stamp)
+            .map_err(|e| eprintln!("Error analyzing {}: {}", path.display(), e))
+            .ok()
+    }
 
-        let mut in_block_comment = false;
+    /// Adds what became of a file to `findings`.
+    fn record(&self, path: &Path, outcome: Outcome, findings: &mut Findings) {
+        let stats = &mut findings.stats;
+        match outcome {
+            Outcome::Ignored => {}
+            Outcome::Skipped(reason) => stats.skip(&reason),
+            Outcome::SetAside {
+                kind,
+                language,
+                stats: file_stats,
+            } => {
+                if let Some(file_stats) = &file_stats {
+                    stats
+                        .files
+                        .push(file_record(path, &language, file_stats, Some(kind)));
+                }
+                self.set_aside(path, &language, kind, file_stats, stats)
+            }
+            Outcome::Counted {
+                language,
+                stats: mut file_stats,
+            } => {
+                stats
+                    .files
+                    .push(file_record(path, &language, &file_stats, None));
+                if language == "Other" {
+                    findings.other_files.push(path.to_path_buf());
+                }
+                match self.config.as_ref().map(|c| c.embedded_languages) {
+                    Some(EmbeddedLanguages::Rollup) => {
+                        stats.update(&language, file_stats.rolled_up())
+                    }
+                    _ => {
+                        let embedded = std::mem::take(&mut file_stats.embedded);
+                        stats.update(&language, file_stats);
+                        for (child, child_stats) in embedded {
+                            stats.add_embedded(&child, child_stats);
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-        for line in content.lines() {
-            let line = line.trim();
-            stats.lines.total += 1;
+    /// Skips a vendored, generated or minified file, or counts it in its own
+    /// section, depending on the config.
+    fn set_aside(
+        &self,
+        path: &Path,
+        language: &str,
+        kind: SetAside,
+        file_stats: Option<FileStats>,
+        stats: &mut LanguageStats,
+    ) {
+        if kind == SetAside::Minified {
+            stats.minified.push(path.to_path_buf());
+        }
+        let file_stats = match (self.handling(kind), file_stats) {
+            (FileHandling::Report, Some(file_stats)) => file_stats,
+            _ => {
+                stats.skip(kind.reason());
+                return;
+            }
+        };
+        let section = if kind == SetAside::Vendored {
+            &mut stats.vendored
+        } else {
+            &mut stats.generated
+        };
+        section
+            .entry(language.to_string())
+            .or_default()
+            .add(&file_stats.rolled_up());
+    }
 
-            if line.is_empty() {
-                stats.lines.blank += 1;
-                continue;
+    /// Minified files are handled like generated ones.
+    fn handling(&self, kind: SetAside) -> FileHandling {
+        self.config.as_ref().map_or(FileHandling::default(), |c| {
+            if kind == SetAside::Vendored {
+                c.vendored_files
+            } else {
+                c.generated_files
             }
+        })
+    }
 
-            if in_block_comment {
-                stats.lines.comment += 1;
-                if line.contains("*/") {
-                    in_block_comment = false;
+    /// Counts the lines of a file, and tells whether it looks minified.
+    fn analyze_file(
+        &self,
+        path: &Path,
+        language: &str,
+        mut stamp: Option<&mut Stamp>,
+    ) -> Result<(FileStats, bool)> {
+        match self.fallback {
+            // Only files that turn out not to be UTF-8 use the legacy encoding
+            Fallback::Encoding(_) => {
+                match self.count_lines(path, language, Fallback::Strict, stamp.as_deref_mut()) {
+                    Err(CodeStatsError::EncodingError(_)) => {
+                        self.count_lines(path, language, self.fallback, stamp)
+                    }
+                    counted => counted,
                 }
-                continue;
             }
+            fallback => self.count_lines(path, language, fallback, stamp),
+        }
+    }
 
-            if line.starts_with("//") || line.starts_with("#") {
-                stats.lines.comment += 1;
-            } else if line.starts_with("/*") {
-                stats.lines.comment += 1;
-                in_block_comment = true;
-            } else {
-                stats.lines.code += 1;
+    /// Counts the lines of a file, hashing it for `stamp` on the way.
+    fn count_lines(
+        &self,
+        path: &Path,
+        language: &str,
+        fallback: Fallback,
+        stamp: Option<&mut Stamp>,
+    ) -> Result<(FileStats, bool)> {
+        let mut reader = if stamp.is_some() {
+            LineReader::open_hashed(path, fallback)?
+        } else {
+            LineReader::open_with(path, fallback)?
+        };
+        let mixed_lines = self
+            .config
+            .as_ref()
+            .map_or(MixedLines::default(), |c| c.mixed_lines);
+        let registry = self.detector.registry();
+        let mut counter = LineCounter::new(registry.syntax(language), mixed_lines);
+        let mut splitter = RegionSplitter::for_language(registry, language);
+        let mut embedded: BTreeMap<&str, (LineCounter, usize)> = BTreeMap::new();
+
+        let mut bytes = 0;
+        let mut whitespace = 0;
+        let mut longest_line = 0;
+        while let Some(raw) = reader.next_line()? {
+            bytes += raw.len();
+            whitespace += raw.bytes().filter(u8::is_ascii_whitespace).count();
+            let line = raw.strip_suffix('\n').unwrap_or(raw);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            longest_line = longest_line.max(line.len());
+            match splitter.as_mut().and_then(|s| s.route(line)) {
+                Some(child) => {
+                    let (child_counter, child_bytes) = embedded.entry(child).or_insert_with(|| {
+                        (LineCounter::new(registry.syntax(child), mixed_lines), 0)
+                    });
+                    child_counter.push(line);
+                    *child_bytes += raw.len();
+                }
+                None => counter.push(line),
             }
         }
 
-        Ok(stats)
+        if let (Some(stamp), Some(hash)) = (stamp, reader.hash()) {
+            stamp.set_hash(hash);
+        }
+
+        let embedded: BTreeMap<String, FileStats> = embedded
+            .into_iter()
+            .map(|(child, (child_counter, bytes))| {
+                let stats = FileStats {
+                    bytes,
+                    lines: child_counter.finish(),
+                    ..Default::default()
+                };
+                (child.to_string(), stats)
+            })
+            .collect();
+        let embedded_bytes: usize = embedded.values().map(|s| s.bytes).sum();
+
+        let lines = counter.finish();
+        let default = MinifiedThresholds::default();
+        let thresholds = self.config.as_ref().map_or(&default, |c| &c.minified);
+        let minified = thresholds.matches(
+            language,
+            bytes,
+            lines.total + embedded.values().map(|s| s.lines.total).sum::<usize>(),
+            longest_line,
+            whitespace,
+        );
+
+        let file_stats = FileStats {
+            bytes: bytes - embedded_bytes,
+            lines,
+            embedded,
+        };
+        Ok((file_stats, minified))
+    }
+}
+
+/// What the walker threads found.
+struct Totals {
+    findings: Mutex<Findings>,
+    /// The first error, which stops the walk
+    error: Mutex<Option<CodeStatsError>>,
+}
+
+/// Hands each walker thread a [`Worker`].
+struct WorkerBuilder<'a> {
+    analyzer: &'a ProjectAnalyzer,
+    cache: Option<&'a Cache>,
+    totals: &'a Totals,
+}
+
+impl<'s> ParallelVisitorBuilder<'s> for WorkerBuilder<'s> {
+    fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
+        Box::new(Worker {
+            analyzer: self.analyzer,
+            cache: self.cache,
+            totals: self.totals,
+            findings: Findings::default(),
+        })
+    }
+}
+
+/// The findings of one walker thread, added to the totals when the walk
+/// is over.
+struct Worker<'a> {
+    analyzer: &'a ProjectAnalyzer,
+    cache: Option<&'a Cache>,
+    totals: &'a Totals,
+    findings: Findings,
+}
+
+impl Worker<'_> {
+    fn visit_entry(&mut self, entry: ignore::DirEntry) -> Result<()> {
+        // The walker visits a directory before queueing what is inside it
+        if entry.depth() > 0 && entry.file_type().is_some_and(|ft| ft.is_dir()) {
+            self.analyzer.detector.enter_dir(entry.path())?;
+        }
+        self.analyzer
+            .process_entry(entry, self.cache, &mut self.findings)
+    }
+}
+
+impl ParallelVisitor for Worker<'_> {
+    fn visit(&mut self, entry: std::result::Result<ignore::DirEntry, ignore::Error>) -> WalkState {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Error accessing entry: {}", e);
+                return WalkState::Continue;
+            }
+        };
+
+        match self.visit_entry(entry) {
+            Ok(()) => WalkState::Continue,
+            Err(e) => {
+                let mut error = self.totals.error.lock().unwrap_or_else(|e| e.into_inner());
+                error.get_or_insert(e);
+                WalkState::Quit
+            }
+        }
+    }
+}
+
+impl Drop for Worker<'_> {
+    fn drop(&mut self) {
+        let mut findings = self
+            .totals
+            .findings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        findings.merge(std::mem::take(&mut self.findings));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Category, CustomLanguage};
+    use std::fs;
+
+    #[test]
+    fn custom_languages_stay_with_their_analyzer() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.foo"), "x = 1\n").unwrap();
+        let root = dir.path().to_str().unwrap();
+
+        let mut custom = ProjectAnalyzer::new();
+        let foo = CustomLanguage {
+            extensions: vec!["foo".to_string()],
+            ..CustomLanguage::default()
+        };
+        custom.set_config(Config {
+            languages: BTreeMap::from([("Foo".to_string(), foo)]),
+            ..Config::default()
+        });
+        let (stats, _) = custom.analyze_project(root).unwrap();
+        assert!(stats.stats.contains_key("Foo"));
+
+        let (stats, _) = ProjectAnalyzer::new().analyze_project(root).unwrap();
+        assert!(!stats.stats.contains_key("Foo"));
+    }
+
+    #[test]
+    fn languages_settled_by_siblings_are_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.h"), "int a;\n").unwrap();
+        fs::write(root.join("a.c"), "int a;\n").unwrap();
+
+        let mut analyzer = ProjectAnalyzer::new();
+        analyzer.set_config(Config {
+            cache: true,
+            ..Config::default()
+        });
+        let language_of_header = |analyzer: &mut ProjectAnalyzer| {
+            let (stats, _) = analyzer.analyze_project(root.to_str().unwrap()).unwrap();
+            let header = stats.files.iter().find(|file| file.path.ends_with("a.h"));
+            header.unwrap().language.clone()
+        };
+        assert_eq!(language_of_header(&mut analyzer), "C");
+
+        fs::remove_file(root.join("a.c")).unwrap();
+        fs::write(root.join("b.cpp"), "int b;\n").unwrap();
+        assert_eq!(language_of_header(&mut analyzer), "C++");
+    }
+
+    #[test]
+    fn undetectable_files_are_skipped_apart_from_categories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join(".gitattributes"), "*.rs -linguist-detectable\n").unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let (stats, _) = ProjectAnalyzer::new()
+            .analyze_project(root.to_str().unwrap())
+            .unwrap();
+        assert!(!stats.stats.contains_key("Rust"));
+        assert_eq!(stats.skipped.get("undetectable"), Some(&1));
+        assert_eq!(stats.skipped.get("programming"), None);
+    }
+
+    #[test]
+    fn data_and_prose_are_tallied_as_skipped_until_included() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.json"), "{}\n").unwrap();
+        fs::write(root.join("a.md"), "# Title\n").unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        let root = root.to_str().unwrap();
+
+        let (stats, _) = ProjectAnalyzer::new().analyze_project(root).unwrap();
+        assert_eq!(stats.total_files, 1);
+        assert!(stats.stats.contains_key("Rust"));
+        assert_eq!(stats.skipped.get("data"), Some(&1));
+        assert_eq!(stats.skipped.get("prose"), Some(&1));
+
+        let mut analyzer = ProjectAnalyzer::new();
+        analyzer.set_config(Config {
+            categories: vec![
+                Category::Programming,
+                Category::Markup,
+                Category::Data,
+                Category::Prose,
+            ],
+            ..Config::default()
+        });
+        let (stats, _) = analyzer.analyze_project(root).unwrap();
+        assert_eq!(stats.total_files, 3);
+        assert_eq!(stats.stats["JSON"].lines.total, 1);
+        assert_eq!(stats.stats["Markdown"].lines.total, 1);
+        assert!(stats.skipped.is_empty());
+    }
+
+    #[test]
+    fn thread_count_does_not_change_the_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["src/core", "web", "vendor/lib", "assets"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        for i in 0..8 {
+            let source = format!("// file {}\nfn f{}() {{}}\n\n", i, i);
+            fs::write(root.join(format!("src/core/m{}.rs", i)), source).unwrap();
+        }
+        fs::write(root.join("src/main.py"), "# main\nprint(1)\n").unwrap();
+        fs::write(
+            root.join("web/App.vue"),
+            "<template>\n<p>hi</p>\n</template>\n<script>\nlet a = 1;\n</script>\n",
+        )
+        .unwrap();
+        fs::write(root.join("vendor/lib/dep.js"), "var x = 1;\n").unwrap();
+        fs::write(root.join("assets/data.json"), "{}\n").unwrap();
+        fs::write(root.join("assets/logo.bin"), [0u8, 159, 146, 150]).unwrap();
+
+        let analyze = |threads| {
+            let mut analyzer = ProjectAnalyzer::new();
+            analyzer.set_threads(threads);
+            let (stats, _) = analyzer.analyze_project(root.to_str().unwrap()).unwrap();
+            stats
+        };
+        let single = analyze(1);
+        let parallel = analyze(4);
+        assert_eq!(single.stats, parallel.stats);
+        assert_eq!(single.files, parallel.files);
+        assert_eq!(single.skipped, parallel.skipped);
+        assert_eq!(single.stats["Rust"].lines.total, 24);
+        assert!(!single.skipped.is_empty());
     }
 }
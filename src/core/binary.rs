@@ -0,0 +1,81 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a file is sniffed, the same window git uses.
+const SNIFF_SIZE: u64 = 8000;
+
+/// Signatures of binary formats that may not have a NUL byte near the start.
+const MAGIC_NUMBERS: &[&[u8]] = &[
+    b"\x7fELF",
+    b"\xca\xfe\xba\xbe", // Java class, Mach-O universal binary
+    b"\xcf\xfa\xed\xfe", // Mach-O 64-bit
+    b"\xce\xfa\xed\xfe", // Mach-O 32-bit
+    b"\x00asm",          // WebAssembly
+    b"SQLite format 3\x00",
+    b"!<arch>\n",        // ar archives: .a, .lib, .deb
+    b"\xd0\xcf\x11\xe0", // OLE: .doc, .xls, .msi
+    b"PK\x03\x04",       // zip: .jar, .docx, .apk
+    b"PK\x05\x06",       // empty zip
+    b"\x1f\x8b",         // gzip
+    b"\xfd7zXZ\x00",
+    b"7z\xbc\xaf\x27\x1c",
+    b"\x28\xb5\x2f\xfd", // zstd
+    b"Rar!\x1a\x07",
+    b"%PDF-",
+    b"\x89PNG",
+    b"\xff\xd8\xff", // JPEG
+    b"GIF87a",
+    b"GIF89a",
+    b"\x1a\x45\xdf\xa3", // Matroska, WebM
+];
+
+/// Byte order marks of UTF-16 and UTF-32 text, which is full of NUL bytes.
+const WIDE_BOMS: &[&[u8]] = &[b"\xff\xfe", b"\xfe\xff", b"\x00\x00\xfe\xff"];
+
+/// Whether the file at `path` holds binary data, judging by its first
+/// bytes rather than its name.
+pub(crate) fn is_binary(path: &Path) -> bool {
+    let mut head = Vec::new();
+    fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_SIZE).read_to_end(&mut head))
+        .is_ok_and(|_| looks_binary(&head))
+}
+
+/// A known signature, or a NUL byte outside wide Unicode text.
+fn looks_binary(head: &[u8]) -> bool {
+    if MAGIC_NUMBERS.iter().any(|magic| head.starts_with(magic)) {
+        return true;
+    }
+    if WIDE_BOMS.iter().any(|bom| head.starts_with(bom)) {
+        return false;
+    }
+    head.contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_and_nul_bytes_mark_binary() {
+        let cases: &[(&[u8], bool)] = &[
+            (b"\x7fELF\x02\x01\x01", true),
+            (b"\x89PNG\r\n\x1a\n", true),
+            (b"PK\x03\x04rest", true),
+            (b"%PDF-1.7\n", true),
+            (b"SQLite format 3\x00", true),
+            (b"fn main() {}\n", false),
+            (b"caf\xc3\xa9\n", false),
+            (b"", false),
+            (b"text\x00more", true),
+            // UTF-16 and UTF-32 text is full of NUL bytes
+            (b"\xff\xfea\x00b\x00", false),
+            (b"\xfe\xff\x00a\x00b", false),
+            (b"\x00\x00\xfe\xff\x00\x00\x00a", false),
+        ];
+        for &(head, binary) in cases {
+            assert_eq!(looks_binary(head), binary, "{:?}", head);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use super::analyzer::Outcome;
+use super::config::Config;
+use super::error::Result;
+use super::gitattributes::LinguistAttributes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use xxhash_rust::xxh3::Xxh3Default;
+
+/// Directory under the analysed root holding the cache.
+pub(crate) const CACHE_DIR: &str = ".codstts-cache";
+const CACHE_FILE: &str = "files.json";
+
+/// The analysed files of an earlier run, so unchanged ones need not be
+/// read again.
+///
+/// Entries are only trusted if they were written by the same codstts
+/// version with the same effective config.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    root: PathBuf,
+    contents: Contents,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Contents {
+    version: String,
+    /// Hash of the config and the Linguist definitions it loads
+    config: u64,
+    /// Entries by path relative to the root, with `/` separators
+    files: HashMap<String, Entry>,
+}
+
+/// A file as it was when it was analysed, and what became of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    size: u64,
+    /// Modification time, in nanoseconds since the Unix epoch
+    modified: u128,
+    hash: u64,
+    /// Hash of the file's Linguist attributes
+    attributes: u64,
+    outcome: Outcome,
+}
+
+/// The state of a file on disk, to compare with its cache entry.
+#[derive(Debug)]
+pub(crate) struct Stamp {
+    path: String,
+    size: u64,
+    modified: u128,
+    /// Taken from the cache entry, computed when the size matches but the
+    /// time does not, as in a fresh checkout, or recorded as the file is
+    /// counted
+    hash: Option<u64>,
+    attributes: u64,
+    /// The language was settled by the files next to it, which entries do
+    /// not track, so the outcome is not cached
+    by_siblings: bool,
+}
+
+impl Cache {
+    /// Reads the cache under `root`, starting afresh if there is none or it
+    /// was written by another version or config.
+    pub(crate) fn load(root: &Path, config: &Config) -> Self {
+        let current = Contents {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config: config_hash(config),
+            files: HashMap::new(),
+        };
+        let path = root.join(CACHE_DIR).join(CACHE_FILE);
+        let contents = fs::File::open(path)
+            .ok()
+            .and_then(|file| serde_json::from_reader::<_, Contents>(BufReader::new(file)).ok())
+            .filter(|cached| cached.version == current.version && cached.config == current.config)
+            .unwrap_or(current);
+        Self {
+            root: root.to_path_buf(),
+            contents,
+        }
+    }
+
+    /// The state of the file at `path` with `attributes`, or `None` if it
+    /// cannot be read.
+    pub(crate) fn stamp(&self, path: &Path, attributes: &LinguistAttributes) -> Option<Stamp> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos());
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        Some(Stamp {
+            path: relative.to_string_lossy().replace('\\', "/"),
+            size: metadata.len(),
+            modified,
+            hash: None,
+            attributes: hash(attributes),
+            by_siblings: false,
+        })
+    }
+
+    /// The outcome recorded for the file at `path` if neither it nor its
+    /// attributes changed since it was stamped as in its entry.
+    pub(crate) fn lookup(&self, path: &Path, stamp: &mut Stamp) -> Option<Outcome> {
+        let entry = self.contents.files.get(&stamp.path)?;
+        if entry.size != stamp.size || entry.attributes != stamp.attributes {
+            return None;
+        }
+        if entry.modified != stamp.modified {
+            let hash = file_hash(path)?;
+            stamp.hash = Some(hash);
+            if entry.hash != hash {
+                return None;
+            }
+        }
+        stamp.hash = Some(entry.hash);
+        Some(entry.outcome.clone())
+    }
+
+    /// Replaces the cache with `entries`, dropping the files that are gone.
+    pub(crate) fn save(mut self, entries: Vec<(String, Entry)>) -> Result<()> {
+        let dir = self.root.join(CACHE_DIR);
+        fs::create_dir_all(&dir)?;
+        // Keep the cache out of version control
+        fs::write(dir.join(".gitignore"), "*\n")?;
+
+        self.contents.files = entries.into_iter().collect();
+        let file = fs::File::create(dir.join(CACHE_FILE))?;
+        serde_json::to_writer(BufWriter::new(file), &self.contents)
+            .map_err(|e| std::io::Error::other(e).into())
+    }
+}
+
+impl Stamp {
+    /// Records the hash of the file's contents, taken as it was read.
+    pub(crate) fn set_hash(&mut self, hash: u64) {
+        self.hash = Some(hash);
+    }
+
+    /// Records that the file's language was settled by the files next to it.
+    pub(crate) fn depends_on_siblings(&mut self) {
+        self.by_siblings = true;
+    }
+
+    /// The cache entry for a file that came to `outcome`, or `None` if the
+    /// file can no longer be read or its outcome depends on other files.
+    pub(crate) fn entry(self, path: &Path, outcome: Outcome) -> Option<(String, Entry)> {
+        if self.by_siblings {
+            return None;
+        }
+        let hash = match self.hash {
+            Some(hash) => hash,
+            None => file_hash(path)?,
+        };
+        let entry = Entry {
+            size: self.size,
+            modified: self.modified,
+            hash,
+            attributes: self.attributes,
+            outcome,
+        };
+        Some((self.path, entry))
+    }
+}
+
+fn hash(value: &impl Hash) -> u64 {
+    let mut hasher = Xxh3Default::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn file_hash(path: &Path) -> Option<u64> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = Xxh3Default::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer).ok()? {
+            0 => return Some(hasher.digest()),
+            read => hasher.update(&buffer[..read]),
+        }
+    }
+}
+
+/// Hashes the settings that affect results, including the contents of a
+/// Linguist `languages.yml`, which may change under the same path.
+fn config_hash(config: &Config) -> u64 {
+    let mut hasher = Xxh3Default::new();
+    // A JSON value sorts map keys, so equal configs hash equally
+    if let Ok(value) = serde_json::to_value(config) {
+        hasher.update(value.to_string().as_bytes());
+    }
+    if let Some(defs) = &config.linguist_defs {
+        if let Ok(contents) = fs::read(defs) {
+            hasher.update(&contents);
+        }
+    }
+    hasher.digest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn touch(path: &Path, seconds: u64) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    /// A cache under `dir` holding an entry for `path` as it is now.
+    fn cached(dir: &Path, path: &Path) -> Cache {
+        let config = Config::default();
+        let cache = Cache::load(dir, &config);
+        let stamp = cache.stamp(path, &LinguistAttributes::default()).unwrap();
+        let entry = stamp.entry(path, Outcome::Ignored).unwrap();
+        cache.save(vec![entry]).unwrap();
+        Cache::load(dir, &config)
+    }
+
+    fn hit(cache: &Cache, path: &Path, attributes: &LinguistAttributes) -> bool {
+        let mut stamp = cache.stamp(path, attributes).unwrap();
+        cache.lookup(path, &mut stamp).is_some()
+    }
+
+    #[test]
+    fn unchanged_files_hit_and_changed_ones_miss() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("a.rs");
+        fs::write(&path, "fn a() {}\n").unwrap();
+        touch(&path, 1_000);
+        let cache = cached(dir, &path);
+        let attributes = LinguistAttributes::default();
+        assert!(hit(&cache, &path, &attributes));
+
+        // A new time alone, as in a fresh checkout, is settled by the hash
+        touch(&path, 2_000);
+        assert!(hit(&cache, &path, &attributes));
+
+        // Same size and new contents
+        fs::write(&path, "fn b() {}\n").unwrap();
+        assert!(!hit(&cache, &path, &attributes));
+
+        fs::write(&path, "fn a() {}\n\n").unwrap();
+        assert!(!hit(&cache, &path, &attributes));
+    }
+
+    #[test]
+    fn changed_attributes_miss() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("a.js");
+        fs::write(&path, "var a;\n").unwrap();
+        let cache = cached(dir, &path);
+        let vendored = LinguistAttributes {
+            vendored: Some(true),
+            ..Default::default()
+        };
+        assert!(!hit(&cache, &path, &vendored));
+    }
+
+    #[test]
+    fn another_config_starts_afresh() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("a.rs");
+        fs::write(&path, "fn a() {}\n").unwrap();
+        cached(dir, &path);
+        let config = Config {
+            modelines: !Config::default().modelines,
+            ..Default::default()
+        };
+        assert!(Cache::load(dir, &config).contents.files.is_empty());
+    }
+
+    #[test]
+    fn missing_files_have_no_stamp() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let cache = Cache::load(dir, &Config::default());
+        let attributes = LinguistAttributes::default();
+        assert!(cache.stamp(&dir.join("gone.rs"), &attributes).is_none());
+    }
+}
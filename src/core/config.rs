@@ -1,8 +1,11 @@
 use super::error::{CodeStatsError, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub ignore_paths: Vec<PathBuf>,
@@ -15,6 +18,240 @@ pub struct Config {
 
     #[serde(default)]
     pub exclude_files: Vec<String>,
+
+    #[serde(default)]
+    pub mixed_lines: MixedLines,
+
+    #[serde(default)]
+    pub embedded_languages: EmbeddedLanguages,
+
+    /// Honour Vim and Emacs modelines when detecting languages
+    #[serde(default = "default_true")]
+    pub modelines: bool,
+
+    /// What happens to third-party code checked into the repository
+    #[serde(default)]
+    pub vendored_files: FileHandling,
+
+    /// What happens to files written by code generators
+    #[serde(default)]
+    pub generated_files: FileHandling,
+
+    /// When a file counts as minified; minified files are handled like
+    /// generated ones
+    #[serde(default)]
+    pub minified: MinifiedThresholds,
+
+    /// Kinds of languages to count; others are skipped and only tallied
+    #[serde(default = "default_categories")]
+    pub categories: Vec<Category>,
+
+    /// Linguist `languages.yml` to load languages from
+    #[serde(default)]
+    pub linguist_defs: Option<PathBuf>,
+
+    /// Encoding of files that are not UTF-8 and have no byte order mark:
+    /// an encoding label such as `windows-1252` or `shift_jis`, or `lossy`
+    /// to replace invalid bytes; unset, such files are reported as errors
+    #[serde(default)]
+    pub fallback_encoding: Option<String>,
+
+    /// Keep the results of unchanged files in `.codstts-cache/` between
+    /// runs
+    #[serde(default)]
+    pub cache: bool,
+
+    /// Project-specific languages, keyed by display name
+    #[serde(default)]
+    pub languages: BTreeMap<String, CustomLanguage>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ignore_paths: Vec::new(),
+            language_mappings: Default::default(),
+            exclude_extensions: Vec::new(),
+            exclude_files: Vec::new(),
+            mixed_lines: MixedLines::default(),
+            embedded_languages: EmbeddedLanguages::default(),
+            modelines: true,
+            vendored_files: FileHandling::default(),
+            generated_files: FileHandling::default(),
+            minified: MinifiedThresholds::default(),
+            categories: default_categories(),
+            linguist_defs: None,
+            fallback_encoding: None,
+            cache: false,
+            languages: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// What happens to vendored or generated files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileHandling {
+    /// Leave them out of the statistics
+    #[default]
+    Exclude,
+    /// Count them in their own section of the detailed view
+    Report,
+}
+
+/// Limits past which a file looks minified. Any one of them is enough.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MinifiedThresholds {
+    /// Languages whose files are checked; hand-written code in other
+    /// languages can have long lines too
+    pub languages: Vec<String>,
+    /// Smaller files are never considered minified
+    pub min_bytes: usize,
+    /// Average bytes per line
+    pub average_line_length: usize,
+    /// Bytes in the longest line
+    pub longest_line: usize,
+    /// Share of whitespace bytes below which a file looks minified
+    pub whitespace_ratio: f64,
+}
+
+impl Default for MinifiedThresholds {
+    fn default() -> Self {
+        Self {
+            languages: ["JavaScript", "CSS", "HTML", "JSON", "SVG"]
+                .map(String::from)
+                .to_vec(),
+            min_bytes: 1024,
+            average_line_length: 200,
+            longest_line: 5000,
+            whitespace_ratio: 0.03,
+        }
+    }
+}
+
+impl MinifiedThresholds {
+    pub(crate) fn matches(
+        &self,
+        language: &str,
+        bytes: usize,
+        lines: usize,
+        longest_line: usize,
+        whitespace: usize,
+    ) -> bool {
+        if !self.languages.iter().any(|l| l == language) {
+            return false;
+        }
+        if bytes < self.min_bytes || lines == 0 {
+            return false;
+        }
+        bytes / lines > self.average_line_length
+            || longest_line > self.longest_line
+            || (whitespace as f64 / bytes as f64) < self.whitespace_ratio
+    }
+}
+
+fn default_categories() -> Vec<Category> {
+    vec![Category::Programming, Category::Markup]
+}
+
+/// The kind of a language, following GitHub Linguist's `type`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    /// General-purpose and build languages
+    #[default]
+    Programming,
+    /// HTML, CSS, templates and the like
+    Markup,
+    /// JSON, YAML, TOML and other configuration or data formats
+    Data,
+    /// Markdown, plain text and other documentation
+    Prose,
+}
+
+impl Category {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Programming => "programming",
+            Category::Markup => "markup",
+            Category::Data => "data",
+            Category::Prose => "prose",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Category {
+    type Err = CodeStatsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "programming" => Ok(Category::Programming),
+            "markup" => Ok(Category::Markup),
+            "data" => Ok(Category::Data),
+            "prose" => Ok(Category::Prose),
+            other => Err(CodeStatsError::InvalidArgument(format!(
+                "unknown category `{}` (expected programming, markup, data or prose)",
+                other
+            ))),
+        }
+    }
+}
+
+/// How a line holding both code and a comment is counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MixedLines {
+    /// Count it as code (cloc, tokei)
+    #[default]
+    Code,
+    /// Count it as a comment
+    Comment,
+    /// Count it as both code and a comment, so the categories can exceed the total
+    Both,
+    /// Count it in its own `mixed` category
+    Separate,
+}
+
+/// Where `<script>`/`<style>` blocks and Markdown code fences are counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddedLanguages {
+    /// Count each region under its own language
+    #[default]
+    Split,
+    /// Count everything under the parent language, with a per-language breakdown
+    Rollup,
+}
+
+/// A language declared in the `[languages.<Name>]` section.
+///
+/// It takes precedence over a built-in language claiming the same
+/// extensions or file names.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CustomLanguage {
+    pub extensions: Vec<String>,
+    pub filenames: Vec<String>,
+    pub line_comments: Vec<String>,
+    /// `[open, close]` pairs
+    pub block_comments: Vec<(String, String)>,
+    /// Quote characters; a backslash escapes the next character
+    pub strings: Vec<String>,
+    /// Colour name or `#rrggbb` hex code
+    pub color: Option<String>,
 }
 
 impl Config {
@@ -41,7 +278,52 @@ impl Config {
         self.exclude_extensions.iter().any(|e| e == extension)
     }
 
+    pub(crate) fn includes_category(&self, category: Category) -> bool {
+        self.categories.contains(&category)
+    }
+
     pub(crate) fn should_ignore_file(&self, filename: &str) -> bool {
         self.exclude_files.iter().any(|f| f == filename)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minified_files_exceed_one_threshold_past_the_size_floor() {
+        let thresholds = MinifiedThresholds::default();
+        // (bytes, lines, longest line, whitespace bytes)
+        let cases = [
+            ((1023, 1, 1023, 0), false),
+            ((4000, 0, 0, 0), false),
+            ((4000, 100, 80, 800), false),
+            ((4000, 10, 400, 800), true),
+            ((60000, 1000, 5001, 12000), true),
+            ((4000, 100, 80, 100), true),
+        ];
+        for ((bytes, lines, longest_line, whitespace), expected) in cases {
+            assert_eq!(
+                thresholds.matches("JavaScript", bytes, lines, longest_line, whitespace),
+                expected,
+                "{} bytes, {} lines, longest {}, {} whitespace",
+                bytes,
+                lines,
+                longest_line,
+                whitespace
+            );
+        }
+    }
+
+    #[test]
+    fn only_listed_languages_can_be_minified() {
+        let mut thresholds = MinifiedThresholds::default();
+        // 18 lines of 4 KB, like generated bindings written out by hand
+        assert!(thresholds.matches("CSS", 4096, 18, 300, 800));
+        assert!(!thresholds.matches("Rust", 4096, 18, 300, 800));
+
+        thresholds.languages.push("Rust".to_string());
+        assert!(thresholds.matches("Rust", 4096, 18, 300, 800));
+    }
+}
@@ -1,202 +1,581 @@
+use super::binary;
 use super::config::Config;
-use super::error::{CodeStatsError, Result};
-use std::collections::HashMap;
+use super::error::Result;
+use super::gitattributes::{Gitattributes, LinguistAttributes, Macros};
+use super::heuristics;
+use super::registry::Registry;
+use super::vendored;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// The language of a file and what settled it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Detection<'a> {
+    pub(crate) language: &'a str,
+    /// The files next to it broke a tie, so the answer may change when
+    /// they do
+    pub(crate) by_siblings: bool,
+}
+
+impl<'a> From<&'a str> for Detection<'a> {
+    fn from(language: &'a str) -> Self {
+        Self {
+            language,
+            by_siblings: false,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub(crate) struct LanguageDetector {
-    vendored_paths: Vec<PathBuf>,
-    documentation_paths: Vec<PathBuf>,
-    generated_paths: Vec<PathBuf>,
-    linguist_overrides: HashMap<PathBuf, String>,
+    /// Languages known to the project being analysed
+    registry: Arc<Registry>,
+    /// Directory being analysed
+    root: PathBuf,
+    /// `.gitattributes` files between the repository top and `root`,
+    /// outermost first
+    outer_attributes: Vec<Gitattributes>,
+    /// `.gitattributes` files by directory, loaded as the walk reaches them;
+    /// the walk visits a directory before anything inside it
+    gitattributes: RwLock<HashMap<PathBuf, Gitattributes>>,
+    /// `.git/info/attributes`, which overrides every `.gitattributes`
+    info_attributes: Option<Gitattributes>,
+    /// Extensions of the files in each directory holding an ambiguous
+    /// file, read once per directory
+    sibling_extensions: RwLock<HashMap<PathBuf, HashSet<String>>>,
+    macros: Macros,
 }
 
 impl LanguageDetector {
     pub(crate) fn new() -> Self {
         Self {
-            vendored_paths: Vec::new(),
-            documentation_paths: Vec::new(),
-            generated_paths: Vec::new(),
-            linguist_overrides: HashMap::new(),
-        }
-    }
-
-    pub(crate) fn load_overrides(&mut self, root: &Path) -> Result<()> {
-        let gitattributes = root.join(".gitattributes");
-        if gitattributes.exists() {
-            let content = fs::read_to_string(&gitattributes).map_err(CodeStatsError::Io)?;
-            for line in content.lines() {
-                if line.contains("linguist-language=") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 {
-                        if let Some(lang) = parts[1].strip_prefix("linguist-language=") {
-                            self.linguist_overrides
-                                .insert(root.join(parts[0]), lang.to_string());
-                        }
-                    }
+            registry: Arc::clone(Registry::builtin()),
+            root: PathBuf::new(),
+            outer_attributes: Vec::new(),
+            gitattributes: RwLock::new(HashMap::new()),
+            info_attributes: None,
+            sibling_extensions: RwLock::new(HashMap::new()),
+            macros: Macros::new(),
+        }
+    }
+
+    /// Prepares to analyse `root` with the languages of `registry`, loading
+    /// the attributes that apply to `root` as a whole: the `.gitattributes`
+    /// files from the repository top down to `root`, and
+    /// `.git/info/attributes`.
+    pub(crate) fn load_overrides(&mut self, root: &Path, registry: Arc<Registry>) -> Result<()> {
+        *self = Self::new();
+        self.registry = registry;
+        self.root = root.to_path_buf();
+
+        let absolute = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let top = absolute
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .unwrap_or(&absolute);
+        let mut dirs: Vec<&Path> = absolute
+            .ancestors()
+            .take_while(|dir| dir.starts_with(top))
+            .collect();
+        dirs.reverse();
+
+        for dir in dirs {
+            // Only the top-level file may define macros
+            let top_level = dir == top;
+            let file = dir.join(".gitattributes");
+            let attributes = if top_level {
+                Gitattributes::load_top_level(&file, root, &mut self.macros)?
+            } else {
+                Gitattributes::load(&file, root, &self.macros)?
+            };
+            if let Some(attributes) = attributes {
+                if dir == absolute {
+                    self.gitattributes
+                        .get_mut()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(root.to_path_buf(), attributes);
+                } else {
+                    let prefix = absolute.strip_prefix(dir).unwrap_or(Path::new(""));
+                    self.outer_attributes.push(attributes.with_prefix(prefix));
                 }
             }
+
+            if top_level {
+                let info = dir.join(".git").join("info").join("attributes");
+                let prefix = absolute.strip_prefix(dir).unwrap_or(Path::new(""));
+                self.info_attributes =
+                    Gitattributes::load_top_level(&info, root, &mut self.macros)?
+                        .map(|attributes| attributes.with_prefix(prefix));
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the `.gitattributes` of a directory below the root as the walk
+    /// enters it.
+    pub(crate) fn enter_dir(&self, dir: &Path) -> Result<()> {
+        if let Some(attributes) =
+            Gitattributes::load(&dir.join(".gitattributes"), dir, &self.macros)?
+        {
+            self.gitattributes
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(dir.to_path_buf(), attributes);
         }
         Ok(())
     }
 
-    pub(crate) fn detect_language(
-        &self,
+    /// The Linguist attributes `path` gets from every attributes file that
+    /// applies to it.
+    ///
+    /// Files closer to `path` override outer ones, and
+    /// `.git/info/attributes` overrides them all.
+    pub(crate) fn attributes(&self, path: &Path) -> LinguistAttributes {
+        let mut attributes = HashMap::new();
+        for gitattributes in &self.outer_attributes {
+            gitattributes.apply(path, &mut attributes);
+        }
+
+        let mut dirs: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .collect();
+        dirs.reverse();
+        let loaded = self.gitattributes.read().unwrap_or_else(|e| e.into_inner());
+        for dir in dirs {
+            if let Some(gitattributes) = loaded.get(dir) {
+                gitattributes.apply(path, &mut attributes);
+            }
+        }
+
+        if let Some(info) = &self.info_attributes {
+            info.apply(path, &mut attributes);
+        }
+        LinguistAttributes::from_attributes(&attributes)
+    }
+
+    pub(crate) fn detect_language<'a>(
+        &'a self,
         path: &Path,
-        config: Option<&Config>,
-    ) -> Result<&'static str> {
+        attributes: &'a LinguistAttributes,
+        config: Option<&'a Config>,
+    ) -> Result<Detection<'a>> {
         // Check for linguist override
-        if let Some(lang) = self.linguist_overrides.get(path) {
-            return Ok(Box::leak(lang.clone().into_boxed_str()));
+        if let Some(lang) = &attributes.language {
+            let language = self.registry.by_alias(lang).unwrap_or(lang);
+            return Ok(language.into());
         }
 
         // Check config language mappings
         if let Some(config) = config {
             if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
                 if let Some(lang) = config.get_language_mapping(ext) {
-                    return Ok(Box::leak(lang.clone().into_boxed_str()));
+                    return Ok(lang.as_str().into());
                 }
             }
         }
 
-        // Check for Dockerfile
-        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-            if file_name.eq_ignore_ascii_case("dockerfile")
-                || file_name.to_lowercase().ends_with(".dockerfile")
-            {
-                return Ok("Dockerfile");
+        // Vim and Emacs modelines override the file name
+        if config.is_none_or(|c| c.modelines) {
+            if let Some(language) = self.language_from_modeline(path) {
+                return Ok(language.into());
+            }
+        }
+
+        // A shebang names the language of a script better than its name,
+        // which may be `build` or `workspace`
+        if path.extension().is_none() {
+            if let Some(language) = self.language_from_shebang(path) {
+                return Ok(language.into());
+            }
+        }
+
+        // Well-known build and CI files
+        if let Some(language) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|name| self.registry.by_filename(name))
+        {
+            return Ok(language.into());
+        }
+
+        // Fallback to extension, then to the interpreter named by a shebang
+        // for unknown extensions
+        let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+            return Ok("Other".into());
+        };
+        // Heuristics only choose among built-in languages, so they are left
+        // out once a custom or Linguist language takes the extension over
+        let builtin_owner =
+            self.registry.by_extension(ext) == Registry::builtin().by_extension(ext);
+        let mut by_siblings = false;
+        let language = if builtin_owner && heuristics::is_ambiguous(ext) {
+            heuristics::disambiguate(path, ext)
+                .or_else(|| {
+                    by_siblings = true;
+                    self.language_from_siblings(path, ext)
+                })
+                .unwrap_or_else(|| self.language_for_extension(path))
+        } else {
+            self.language_for_extension(path)
+        };
+        if language == "Other" {
+            if let Some(language) = self.language_from_shebang(path) {
+                return Ok(language.into());
             }
         }
+        Ok(Detection {
+            language,
+            by_siblings,
+        })
+    }
+
+    /// Breaks a tie by the extensions next to `path`, listing each
+    /// directory only once however many ambiguous files it holds.
+    fn language_from_siblings(&self, path: &Path, ext: &str) -> Option<&'static str> {
+        let dir = path.parent()?;
+        let listed = self
+            .sibling_extensions
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(dir)
+            .map(|siblings| heuristics::from_siblings(ext, siblings));
+        if let Some(language) = listed {
+            return language;
+        }
 
-        // Fallback to extension
-        Ok(path
-            .extension()
+        let siblings = heuristics::extensions_in(dir);
+        let language = heuristics::from_siblings(ext, &siblings);
+        self.sibling_extensions
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(dir.to_path_buf(), siblings);
+        language
+    }
+
+    fn language_for_extension(&self, path: &Path) -> &str {
+        path.extension()
             .and_then(|ext| ext.to_str())
-            .map(|ext| match ext {
-                "py" | "pyi" | "pyx" => "Python",
-                "js" | "mjs" => "JavaScript",
-                "ts" | "mts" | "cts" => "TypeScript",
-                "tsx" | "jsx" => "React",
-                "css" | "scss" | "sass" | "less" => "CSS",
-                "html" | "htm" | "xhtml" => "HTML",
-                "sh" | "bash" | "zsh" => "Shell",
-                "rs" => "Rust",
-                "go" | "mod" => "Go",
-                "java" | "kt" | "kts" => "Java",
-                "vue" => "Vue",
-                "rb" | "rake" | "gemspec" => "Ruby",
-                "php" | "php5" | "phtml" => "PHP",
-                "c" | "h" => "C",
-                "cpp" | "hpp" | "cc" | "cxx" | "hxx" => "C++",
-                "swift" => "Swift",
-                "m" | "mm" => "Objective-C",
-                "cs" => "C#",
-                "pl" | "pm" => "Perl",
-                "scala" => "Scala",
-                "lua" => "Lua",
-                "r" | "R" => "R",
-                "dart" => "Dart",
-                "ex" | "exs" => "Elixir",
-                "hs" => "Haskell",
-                "dockerfile" => "Dockerfile",
-                _ => "Other",
+            .and_then(|ext| self.registry.by_extension(ext))
+            .unwrap_or("Other")
+    }
+
+    fn language_from_shebang(&self, path: &Path) -> Option<&str> {
+        let file = fs::File::open(path).ok()?;
+        let mut first_line = Vec::new();
+        BufReader::new(file)
+            .take(256)
+            .read_until(b'\n', &mut first_line)
+            .ok()?;
+        let first_line = String::from_utf8_lossy(&first_line);
+        self.registry
+            .by_interpreter(Self::parse_shebang(&first_line)?)
+    }
+
+    /// The interpreter a `#!` line runs, without its path or version.
+    fn parse_shebang(line: &str) -> Option<&str> {
+        let mut words = line.strip_prefix("#!")?.split_whitespace();
+
+        let mut program = words.next()?.rsplit('/').next()?;
+        if program == "env" {
+            // Skip env's own options and variable assignments, e.g.
+            // `#!/usr/bin/env -S FOO=1 python3 -u`
+            program = loop {
+                let word = words.next()?;
+                if word == "-u" || word == "--unset" {
+                    words.next();
+                } else if !word.starts_with('-') && !word.contains('=') {
+                    break word;
+                }
+            };
+        }
+
+        // `python3.11` and `pypy3` are still Python
+        Some(program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+    }
+
+    /// Looks for a Vim or Emacs modeline in the first or last five lines.
+    fn language_from_modeline(&self, path: &Path) -> Option<&str> {
+        const WINDOW: u64 = 4096;
+        const LINES: usize = 5;
+
+        let mut file = fs::File::open(path).ok()?;
+        let len = file.metadata().ok()?.len();
+        let mut head = Vec::new();
+        (&mut file).take(WINDOW).read_to_end(&mut head).ok()?;
+        let head = String::from_utf8_lossy(&head).into_owned();
+
+        let tail = if len > WINDOW {
+            let mut tail = Vec::new();
+            file.seek(SeekFrom::End(-(WINDOW as i64))).ok()?;
+            file.read_to_end(&mut tail).ok()?;
+            String::from_utf8_lossy(&tail).into_owned()
+        } else {
+            head.clone()
+        };
+
+        let last_lines: Vec<&str> = tail.lines().rev().take(LINES).collect();
+        head.lines()
+            .take(LINES)
+            .chain(last_lines.into_iter().rev())
+            .find_map(|line| {
+                Self::parse_vim_modeline(line).or_else(|| Self::parse_emacs_modeline(line))
+            })
+            .and_then(|name| {
+                self.registry
+                    .by_alias(name.strip_suffix("-mode").unwrap_or(name))
             })
-            .unwrap_or("Other"))
     }
 
-    pub(crate) fn should_ignore(&self, path: &Path) -> bool {
-        if self.vendored_paths.iter().any(|p| path.starts_with(p))
-            || self.documentation_paths.iter().any(|p| path.starts_with(p))
-            || self.generated_paths.iter().any(|p| path.starts_with(p))
-        {
+    /// `vim: set ft=ruby:`, `vi: filetype=python` and friends.
+    fn parse_vim_modeline(line: &str) -> Option<&str> {
+        let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+            line.match_indices(marker)
+                .find(|&(pos, _)| pos == 0 || line[..pos].ends_with(char::is_whitespace))
+                .map(|(pos, _)| pos + marker.len())
+        })?;
+
+        line[start..]
+            .split(|c: char| c == ':' || c.is_whitespace())
+            .find_map(|option| {
+                let (key, value) = option.split_once('=')?;
+                matches!(key, "ft" | "filetype" | "syn" | "syntax").then_some(value)
+            })
+            .filter(|value| !value.is_empty())
+    }
+
+    /// `-*- mode: python -*-` or the short form `-*- python -*-`.
+    fn parse_emacs_modeline(line: &str) -> Option<&str> {
+        let (_, rest) = line.split_once("-*-")?;
+        let (vars, _) = rest.split_once("-*-")?;
+
+        let mode = if vars.contains(':') {
+            vars.split(';').find_map(|var| {
+                let (key, value) = var.split_once(':')?;
+                key.trim().eq_ignore_ascii_case("mode").then_some(value)
+            })?
+        } else {
+            vars
+        };
+        Some(mode.trim()).filter(|mode| !mode.is_empty())
+    }
+
+    /// Whether `path` is third-party code, by `linguist-vendored` or else
+    /// by the built-in path patterns.
+    pub(crate) fn is_vendored(&self, path: &Path, attributes: &LinguistAttributes) -> bool {
+        attributes
+            .vendored
+            .unwrap_or_else(|| vendored::is_vendored(self.relative(path)))
+    }
+
+    /// Whether `path` was written by a tool, by `linguist-generated` or else
+    /// by its name and header.
+    pub(crate) fn is_generated(&self, path: &Path, attributes: &LinguistAttributes) -> bool {
+        attributes
+            .generated
+            .unwrap_or_else(|| vendored::is_generated(path, self.relative(path)))
+    }
+
+    /// Whether `path` holds binary data, by the `binary` attribute or else
+    /// by its first bytes.
+    pub(crate) fn is_binary(&self, path: &Path, attributes: &LinguistAttributes) -> bool {
+        attributes.binary || binary::is_binary(path)
+    }
+
+    pub(crate) fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    pub(crate) fn should_ignore(&self, path: &Path, attributes: &LinguistAttributes) -> bool {
+        if attributes.documentation == Some(true) {
             return true;
         }
 
-        // Built-in ignore rules
-        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-            match file_name {
-                "Cargo.toml" | "Cargo.lock" | "package.json" | "package-lock.json"
-                | "yarn.lock" | "Gemfile" | "Gemfile.lock" | "requirements.txt" | ".gitignore"
-                | ".gitattributes" | ".editorconfig" | "composer.json" | "composer.lock"
-                | "poetry.lock" | "README.md" | "readme.md" | "README" | "LICENSE"
-                | "CHANGELOG.md" => return true,
-                _ => {
-                    if (file_name.contains("README") || file_name.contains("readme"))
-                        && file_name.ends_with(".md")
-                    {
-                        return true;
-                    }
-                }
-            }
+        let registry = &self.registry;
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|file_name| registry.is_ignored_file(file_name))
+            || path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| registry.is_ignored_extension(ext))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::CustomLanguage;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn shebangs_name_the_interpreter() {
+        let cases = [
+            ("#!/bin/sh", Some("sh")),
+            ("#! /bin/bash -e", Some("bash")),
+            ("#!/usr/bin/env python3", Some("python")),
+            ("#!/usr/bin/env python3.11", Some("python")),
+            ("#!/usr/bin/env -S FOO=1 python3 -u", Some("python")),
+            ("#!/usr/bin/env -u HOME ruby", Some("ruby")),
+            ("#!/usr/bin/env --unset=HOME node", Some("node")),
+            ("#!/usr/local/bin/pypy3", Some("pypy")),
+            ("#!/usr/bin/env", None),
+            ("#!/usr/bin/env -i", None),
+            ("#!", None),
+            ("# comment", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(LanguageDetector::parse_shebang(line), expected, "{}", line);
         }
+    }
 
-        // Standard ignored extensions
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            matches!(
-                ext,
-                "exe"
-                    | "dll"
-                    | "so"
-                    | "dylib"
-                    | "pyc"
-                    | "jar"
-                    | "war"
-                    | "ear"
-                    | "zip"
-                    | "tar"
-                    | "gz"
-                    | "7z"
-                    | "rar"
-                    | "pdf"
-                    | "doc"
-                    | "docx"
-                    | "xls"
-                    | "xlsx"
-                    | "png"
-                    | "jpg"
-                    | "jpeg"
-                    | "gif"
-                    | "bmp"
-                    | "ico"
-                    | "svg"
-                    | "ttf"
-                    | "otf"
-                    | "woff"
-                    | "woff2"
-                    | "eot"
-                    | "mp3"
-                    | "mp4"
-                    | "avi"
-                    | "mov"
-                    | "wmv"
-                    | "wav"
-                    | "flac"
-                    | "ogg"
-                    | "webm"
-                    | "lock"
-                    | "proto"
-                    | "md"
-                    | "markdown"
-                    | "rst"
-                    | "txt"
-                    | "adoc"
-                    | "asciidoc"
-                    | "json"
-                    | "yaml"
-                    | "yml"
-                    | "xml"
-                    | "toml"
-                    | "ini"
-                    | "cfg"
-                    | "conf"
-                    | "properties"
-                    | "prop"
-                    | "env"
-                    | "bat"
-            )
-        } else {
-            false
+    #[test]
+    fn vim_modelines_name_the_filetype() {
+        let cases = [
+            ("# vim: set ft=ruby:", Some("ruby")),
+            ("// vi: filetype=python", Some("python")),
+            ("/* ex: syntax=c */", Some("c")),
+            ("# vim: set ts=4 sw=4 ft=perl:", Some("perl")),
+            ("vim:syn=sh", Some("sh")),
+            ("# vim: set ts=4:", None),
+            ("# vim: ft=", None),
+            ("let svim: ft=ruby", None),
+            ("# ft=ruby", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(
+                LanguageDetector::parse_vim_modeline(line),
+                expected,
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn emacs_modelines_name_the_mode() {
+        let cases = [
+            ("# -*- mode: python -*-", Some("python")),
+            ("/* -*- Mode: C; tab-width: 4 -*- */", Some("C")),
+            ("# -*- coding: latin-1; mode: ruby -*-", Some("ruby")),
+            (";; -*- lisp -*-", Some("lisp")),
+            ("# -*- coding: utf-8 -*-", None),
+            ("# -*- -*-", None),
+            ("# -*- python", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(
+                LanguageDetector::parse_emacs_modeline(line),
+                expected,
+                "{}",
+                line
+            );
         }
     }
+
+    /// The `linguist-language` each of `files` under `root` gets.
+    fn languages(detector: &LanguageDetector, root: &Path, files: &[&str]) -> Vec<String> {
+        files
+            .iter()
+            .map(|file| {
+                let attributes = detector.attributes(&root.join(file));
+                attributes.language.unwrap_or_default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn closer_attributes_files_win_and_info_attributes_win_over_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let top = dir.path();
+        let sub = top.join("sub");
+        fs::create_dir_all(top.join(".git/info")).unwrap();
+        fs::create_dir_all(sub.join("deep")).unwrap();
+        fs::write(
+            top.join(".gitattributes"),
+            "*.x linguist-language=Ruby\n*.y linguist-language=Ruby\n*.z linguist-language=Ruby\n",
+        )
+        .unwrap();
+        fs::write(
+            sub.join(".gitattributes"),
+            "*.x linguist-language=Python\n*.y linguist-language=Python\n",
+        )
+        .unwrap();
+        fs::write(
+            top.join(".git/info/attributes"),
+            "*.y linguist-language=Go\n",
+        )
+        .unwrap();
+
+        // Analysing the repository top, the walk enters `sub` itself
+        let mut detector = LanguageDetector::new();
+        detector
+            .load_overrides(top, Arc::clone(Registry::builtin()))
+            .unwrap();
+        detector.enter_dir(&sub).unwrap();
+        assert_eq!(
+            languages(
+                &detector,
+                top,
+                &["a.x", "sub/deep/a.x", "sub/a.y", "sub/a.z"]
+            ),
+            ["Ruby", "Python", "Go", "Ruby"]
+        );
+
+        // Analysing `sub`, the files above it still apply
+        detector
+            .load_overrides(&sub, Arc::clone(Registry::builtin()))
+            .unwrap();
+        assert_eq!(
+            languages(&detector, &sub, &["a.x", "deep/a.x", "a.y", "a.z"]),
+            ["Python", "Python", "Go", "Ruby"]
+        );
+    }
+
+    #[test]
+    fn custom_languages_take_ambiguous_extensions_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flow.r");
+        fs::write(&path, "x <- 1\n").unwrap();
+        let attributes = LinguistAttributes::default();
+
+        let mut detector = LanguageDetector::new();
+        detector
+            .load_overrides(dir.path(), Arc::clone(Registry::builtin()))
+            .unwrap();
+        assert_eq!(
+            detector
+                .detect_language(&path, &attributes, None)
+                .unwrap()
+                .language,
+            "R"
+        );
+
+        let flow = CustomLanguage {
+            extensions: vec!["r".to_string()],
+            ..CustomLanguage::default()
+        };
+        let config = Config {
+            languages: BTreeMap::from([("Flow".to_string(), flow)]),
+            ..Config::default()
+        };
+        detector
+            .load_overrides(dir.path(), Registry::configured(&config).unwrap())
+            .unwrap();
+        assert_eq!(
+            detector
+                .detect_language(&path, &attributes, Some(&config))
+                .unwrap()
+                .language,
+            "Flow"
+        );
+    }
 }
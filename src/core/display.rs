@@ -1,41 +1,122 @@
-use super::stats::{LanguageStats, RoundToDecimals};
+use super::config::Category;
+use super::error::{CodeStatsError, Result};
+use super::registry::Registry;
+use super::stats::{DirStats, FileRecord, FileStats, LanguageStats, RoundToDecimals};
 use colored::*;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Column the file table is sorted by, most telling first: names
+/// alphabetically, sizes and counts largest first, and the comment ratio
+/// least commented first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileSort {
+    Path,
+    Language,
+    Bytes,
+    #[default]
+    Lines,
+    Code,
+    Comments,
+    Blank,
+    /// Share of the non-blank lines that are comments or docs
+    CommentRatio,
+}
+
+impl FileSort {
+    /// Orders two files, each with its stats rolled up.
+    fn compare(
+        self,
+        (a, x): &(&FileRecord, FileStats),
+        (b, y): &(&FileRecord, FileStats),
+    ) -> Ordering {
+        match self {
+            FileSort::Path => a.path.cmp(&b.path),
+            FileSort::Language => a.language.cmp(&b.language),
+            FileSort::Bytes => y.bytes.cmp(&x.bytes),
+            FileSort::Lines => y.lines.total.cmp(&x.lines.total),
+            FileSort::Code => y.lines.code.cmp(&x.lines.code),
+            FileSort::Comments => comments(y).cmp(&comments(x)),
+            FileSort::Blank => y.lines.blank.cmp(&x.lines.blank),
+            FileSort::CommentRatio => comment_ratio(x).total_cmp(&comment_ratio(y)),
+        }
+        .then_with(|| a.path.cmp(&b.path))
+    }
+}
+
+impl FromStr for FileSort {
+    type Err = CodeStatsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "path" => Ok(FileSort::Path),
+            "language" => Ok(FileSort::Language),
+            "bytes" => Ok(FileSort::Bytes),
+            "lines" => Ok(FileSort::Lines),
+            "code" => Ok(FileSort::Code),
+            "comments" => Ok(FileSort::Comments),
+            "blank" => Ok(FileSort::Blank),
+            "comment-ratio" => Ok(FileSort::CommentRatio),
+            other => Err(CodeStatsError::InvalidArgument(format!(
+                "unknown sort column `{}` (expected path, language, bytes, lines, code, \
+                 comments, blank or comment-ratio)",
+                other
+            ))),
+        }
+    }
+}
+
+fn comments(stats: &FileStats) -> usize {
+    stats.lines.comment + stats.lines.doc
+}
+
+fn comment_ratio(stats: &FileStats) -> f64 {
+    let non_blank = stats.lines.total - stats.lines.blank;
+    if non_blank == 0 {
+        return 0.0;
+    }
+    comments(stats) as f64 / non_blank as f64
+}
 
 pub struct StatsDisplay;
 
 impl StatsDisplay {
-    pub(crate) fn get_language_color(language: &str) -> Color {
-        match language {
-            "Python" => Color::Blue,
-            "JavaScript" => Color::Yellow,
-            "CSS" => Color::Magenta,
-            "HTML" => Color::Red,
-            "Shell" => Color::Green,
-            "Rust" => Color::BrightRed,
-            "Go" => Color::Cyan,
-            "Java" => Color::BrightYellow,
-            "Vue" => Color::Green,
-            "React" => Color::Blue,
-            "TypeScript" => Color::Blue,
-            "Ruby" => Color::Red,
-            "PHP" => Color::Magenta,
-            "C" => Color::Blue,
-            "C++" => Color::Magenta,
-            "Dockerfile" => Color::Cyan,
-            _ => Color::White,
+    /// The colour of `language` in `colors`, the definitions the stats were
+    /// counted with, or else the built-in ones: a colour name or a `#rrggbb`
+    /// hex code, falling back to white.
+    pub(crate) fn get_language_color(colors: &HashMap<String, String>, language: &str) -> Color {
+        let color = colors
+            .get(language)
+            .map(String::as_str)
+            .or_else(|| Registry::builtin().color(language));
+        let Some(color) = color else {
+            return Color::White;
+        };
+        match color.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => u32::from_str_radix(hex, 16)
+                .map(|rgb| Color::TrueColor {
+                    r: (rgb >> 16) as u8,
+                    g: (rgb >> 8) as u8,
+                    b: rgb as u8,
+                })
+                .unwrap_or(Color::White),
+            _ => color.parse().unwrap_or(Color::White),
         }
     }
 
     pub fn print_simple_view(stats: &LanguageStats) {
+        let colors = &stats.colors;
         println!("\n{}", "Languages".bold());
 
         let total_bytes: usize = stats.stats.values().map(|s| s.bytes).sum();
         let mut langs: Vec<_> = stats.stats.iter().collect();
-        langs.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes));
+        langs.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.bytes));
 
         let mut percentages: Vec<f64> = langs
             .iter()
-            .map(|(_, stats)| (stats.bytes as f64 / total_bytes as f64 * 100.0))
+            .map(|(_, stats)| stats.bytes as f64 / total_bytes as f64 * 100.0)
             .collect();
 
         for percentage in percentages.iter_mut() {
@@ -58,12 +139,15 @@ impl StatsDisplay {
         for ((language, _), percentage) in langs.iter().zip(percentages.iter()) {
             let bar_length = (percentage * bar_width as f64 / 100.0).round() as usize;
             let bar_segment = "█".repeat(bar_length);
-            print!("{}", bar_segment.color(Self::get_language_color(language)));
+            print!(
+                "{}",
+                bar_segment.color(Self::get_language_color(colors, language))
+            );
         }
         println!("\n");
 
         for ((language, stats), percentage) in langs.iter().zip(percentages.iter()) {
-            let color = Self::get_language_color(language);
+            let color = Self::get_language_color(colors, language);
             println!(
                 "{} {} {:.1}% ({} bytes)",
                 "●".color(color),
@@ -72,10 +156,12 @@ impl StatsDisplay {
                 stats.bytes
             );
         }
+        Self::print_skipped(stats);
         println!();
     }
 
     pub fn print_detailed_view(stats: &LanguageStats) {
+        let colors = &stats.colors;
         println!("\n{}", "📊 Code Statistics".bright_green().bold());
         println!(
             "{} {}",
@@ -84,34 +170,309 @@ impl StatsDisplay {
         );
 
         let mut langs: Vec<_> = stats.stats.iter().collect();
-        langs.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes));
+        langs.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.bytes));
 
         println!("\n{}", "Language Distribution:".bright_yellow().bold());
 
         for (language, stats) in langs {
-            let color = Self::get_language_color(language);
+            let color = Self::get_language_color(colors, language);
             println!("\n{}", language.color(color).bold());
             println!("  {} {} bytes", "Size:".bright_white(), stats.bytes);
             println!("  {} {}", "Total lines:".bright_white(), stats.lines.total);
             println!("  {} {}", "Code:".bright_green(), stats.lines.code);
             println!("  {} {}", "Comments:".bright_yellow(), stats.lines.comment);
+            println!("  {} {}", "Docs:".bright_cyan(), stats.lines.doc);
+            if stats.lines.mixed > 0 {
+                println!("  {} {}", "Mixed:".bright_magenta(), stats.lines.mixed);
+            }
             println!("  {} {}", "Blank:".bright_black(), stats.lines.blank);
 
             let code_percent =
                 (stats.lines.code as f64 / stats.lines.total as f64 * 100.0) as usize;
             let comment_percent =
                 (stats.lines.comment as f64 / stats.lines.total as f64 * 100.0) as usize;
+            let doc_percent = (stats.lines.doc as f64 / stats.lines.total as f64 * 100.0) as usize;
+            let mixed_percent =
+                (stats.lines.mixed as f64 / stats.lines.total as f64 * 100.0) as usize;
             let blank_percent =
                 (stats.lines.blank as f64 / stats.lines.total as f64 * 100.0) as usize;
 
             let bar = format!(
-                "  {}{}{}",
+                "  {}{}{}{}{}",
                 "█".repeat(code_percent / 2).bright_green(),
                 "█".repeat(comment_percent / 2).bright_yellow(),
+                "█".repeat(doc_percent / 2).bright_cyan(),
+                "█".repeat(mixed_percent / 2).bright_magenta(),
                 "█".repeat(blank_percent / 2).bright_black()
             );
             println!("{}", bar);
+
+            for (child, child_stats) in &stats.embedded {
+                println!(
+                    "  {} {} {} lines ({} code)",
+                    "↳".bright_black(),
+                    child.color(Self::get_language_color(colors, child)),
+                    child_stats.lines.total,
+                    child_stats.lines.code
+                );
+            }
+        }
+
+        Self::print_section("Vendored", &stats.vendored, &[], colors);
+        Self::print_section(
+            "Minified/generated",
+            &stats.generated,
+            &stats.minified,
+            colors,
+        );
+
+        Self::print_skipped(stats);
+        println!();
+    }
+
+    /// Says how many files were left out and why, such as `3 binary`.
+    fn print_skipped(stats: &LanguageStats) {
+        if stats.skipped.is_empty() {
+            return;
+        }
+        let skipped: Vec<String> = stats
+            .skipped
+            .iter()
+            .map(|(reason, files)| format!("{} {}", files, reason))
+            .collect();
+        let hint = if stats.skipped.keys().any(|r| r.parse::<Category>().is_ok()) {
+            " (count other categories with --include-categories)"
+        } else {
+            ""
+        };
+        println!(
+            "\n{} {} files{}",
+            "Skipped:".bright_black(),
+            skipped.join(", "),
+            hint
+        );
+    }
+
+    /// Prints one row per file read, with embedded regions counted in
+    /// their file.
+    pub fn print_files(stats: &LanguageStats, sort: FileSort, reverse: bool) {
+        let colors = &stats.colors;
+        let mut files: Vec<(&FileRecord, FileStats)> = stats
+            .files
+            .iter()
+            .map(|file| (file, file.stats.clone().rolled_up()))
+            .collect();
+        files.sort_by(|a, b| sort.compare(a, b));
+        if reverse {
+            files.reverse();
+        }
+
+        println!(
+            "\n{} {}",
+            "📄 Files".bright_green().bold(),
+            format!("({})", files.len()).bright_black()
+        );
+        let language_width = files
+            .iter()
+            .map(|(file, _)| file.language.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Language".len());
+        println!(
+            "{}",
+            format!(
+                "{:>8} {:>8} {:>8} {:>8} {:>10} {:>6}  {:<language_width$}  Path",
+                "Lines", "Code", "Comments", "Blank", "Bytes", "Comm%", "Language"
+            )
+            .bright_white()
+            .bold()
+        );
+
+        for (file, whole) in files {
+            let flags: String = [
+                (file.vendored, "vendored"),
+                (file.generated, "generated"),
+                (file.minified, "minified"),
+            ]
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, flag)| format!("  {}", flag))
+            .collect();
+            println!(
+                "{:>8} {:>8} {:>8} {:>8} {:>10} {:>5.1}%  {}  {}{}",
+                whole.lines.total,
+                whole.lines.code.to_string().bright_green(),
+                comments(&whole).to_string().bright_yellow(),
+                whole.lines.blank.to_string().bright_black(),
+                whole.bytes,
+                comment_ratio(&whole) * 100.0,
+                format!("{:<language_width$}", file.language)
+                    .color(Self::get_language_color(colors, &file.language)),
+                file.path.display(),
+                flags.bright_black()
+            );
+        }
+        println!();
+    }
+
+    /// Prints a directory tree from `LanguageStats::by_dir` of `stats`, each
+    /// directory with its line counts and language mix, `root` naming the
+    /// top.
+    pub fn print_tree(stats: &LanguageStats, tree: &DirStats, root: &str) {
+        let colors = &stats.colors;
+        println!("\n{}", "🌳 Directories".bright_green().bold());
+        let mut rows = Vec::new();
+        Self::tree_rows(
+            tree,
+            format!("{}/", root.trim_end_matches('/')),
+            "",
+            &mut rows,
+        );
+        let width = rows
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (label, dir) in rows {
+            let total = dir.total();
+            let mut languages: Vec<_> = dir.languages.iter().collect();
+            languages.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.lines.total));
+            let mix: Vec<String> = languages
+                .iter()
+                .map(|(language, stats)| {
+                    let percent = if total.lines.total == 0 {
+                        0.0
+                    } else {
+                        stats.lines.total as f64 / total.lines.total as f64 * 100.0
+                    };
+                    format!(
+                        "{} {:.1}%",
+                        format!("● {}", language).color(Self::get_language_color(colors, language)),
+                        percent
+                    )
+                })
+                .collect();
+            println!(
+                "{:<width$}  {:>6} files {:>9} lines {:>9} code  {}",
+                label,
+                dir.files.to_string().bright_white(),
+                total.lines.total,
+                total.lines.code.to_string().bright_green(),
+                mix.join("  ")
+            );
         }
         println!();
     }
+
+    /// Flattens `dir` and its subdirectories into labelled rows, drawing
+    /// the branches of the tree in front of each name.
+    fn tree_rows<'a>(
+        dir: &'a DirStats,
+        label: String,
+        indent: &str,
+        rows: &mut Vec<(String, &'a DirStats)>,
+    ) {
+        rows.push((label, dir));
+        let last = dir.children.len().saturating_sub(1);
+        for (i, (name, child)) in dir.children.iter().enumerate() {
+            let (branch, below) = if i == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let label = format!("{}{}{}/", indent, branch, name);
+            Self::tree_rows(child, label, &format!("{}{}", indent, below), rows);
+        }
+    }
+
+    /// Lists files counted apart from the project's own code, by language,
+    /// followed by the minified files, which are listed even when skipped.
+    fn print_section(
+        title: &str,
+        section: &BTreeMap<String, FileStats>,
+        minified: &[PathBuf],
+        colors: &HashMap<String, String>,
+    ) {
+        if section.is_empty() && minified.is_empty() {
+            return;
+        }
+        println!("\n{}", format!("{}:", title).bright_yellow().bold());
+        for (language, stats) in section {
+            println!(
+                "  {} {} bytes, {} lines ({} code)",
+                language.color(Self::get_language_color(colors, language)),
+                stats.bytes,
+                stats.lines.total,
+                stats.lines.code
+            );
+        }
+        for path in minified {
+            println!("  {} {}", "minified".bright_black(), path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::stats::LineStats;
+
+    /// A file with `(total, code, comment, doc, blank)` lines.
+    fn record(path: &str, language: &str, bytes: usize, lines: [usize; 5]) -> FileRecord {
+        let [total, code, comment, doc, blank] = lines;
+        FileRecord {
+            path: PathBuf::from(path),
+            language: language.to_string(),
+            stats: FileStats {
+                bytes,
+                lines: LineStats {
+                    total,
+                    code,
+                    comment,
+                    doc,
+                    mixed: 0,
+                    blank,
+                },
+                embedded: BTreeMap::new(),
+            },
+            vendored: false,
+            generated: false,
+            minified: false,
+        }
+    }
+
+    #[test]
+    fn files_sort_by_each_column_with_the_path_as_tiebreaker() {
+        let records = [
+            record("b.rs", "Rust", 300, [30, 20, 5, 0, 5]),
+            record("a.py", "Python", 100, [10, 2, 6, 1, 1]),
+            record("c.go", "Go", 200, [30, 25, 0, 0, 5]),
+        ];
+        let cases = [
+            ("path", ["a.py", "b.rs", "c.go"]),
+            ("language", ["c.go", "a.py", "b.rs"]),
+            ("bytes", ["b.rs", "c.go", "a.py"]),
+            ("lines", ["b.rs", "c.go", "a.py"]),
+            ("code", ["c.go", "b.rs", "a.py"]),
+            ("comments", ["a.py", "b.rs", "c.go"]),
+            ("blank", ["b.rs", "c.go", "a.py"]),
+            ("Comment-Ratio", ["c.go", "b.rs", "a.py"]),
+        ];
+
+        for (column, expected) in cases {
+            let sort: FileSort = column.parse().unwrap();
+            let mut files: Vec<(&FileRecord, FileStats)> = records
+                .iter()
+                .map(|file| (file, file.stats.clone()))
+                .collect();
+            files.sort_by(|a, b| sort.compare(a, b));
+            let paths: Vec<_> = files
+                .iter()
+                .map(|(file, _)| file.path.to_str().unwrap())
+                .collect();
+            assert_eq!(paths, expected, "sorted by {}", column);
+        }
+        assert!("ratio".parse::<FileSort>().is_err());
+    }
 }
@@ -0,0 +1,221 @@
+use super::registry::{Embeds, Registry};
+
+/// An embedded region that is currently open.
+#[derive(Debug)]
+struct Region<'a> {
+    /// `None` for regions whose language we do not know, which stay with
+    /// the parent language
+    language: Option<&'a str>,
+    end: End,
+}
+
+#[derive(Debug)]
+enum End {
+    /// A closing tag such as `</script`, matched case-insensitively
+    Tag(&'static str),
+    /// A Markdown fence: at least `len` repetitions of `marker`
+    Fence { marker: char, len: usize },
+}
+
+/// Routes the lines of a single-file component, HTML page or Markdown
+/// document to the language they are written in.
+///
+/// Tag and fence lines belong to the parent; only the lines between them
+/// are handed to the embedded language.
+#[derive(Debug)]
+pub(crate) struct RegionSplitter<'a> {
+    registry: &'a Registry,
+    markdown: bool,
+    region: Option<Region<'a>>,
+}
+
+impl<'a> RegionSplitter<'a> {
+    /// Returns a splitter for languages that embed others, or `None`.
+    pub(crate) fn for_language(registry: &'a Registry, language: &str) -> Option<Self> {
+        let embeds = registry.get(language)?.embeds?;
+        Some(Self {
+            registry,
+            markdown: embeds == Embeds::Markdown,
+            region: None,
+        })
+    }
+
+    /// Returns the embedded language `line` is written in, or `None` if it
+    /// belongs to the parent.
+    pub(crate) fn route(&mut self, line: &str) -> Option<&'a str> {
+        match &self.region {
+            Some(region) => {
+                if Self::closes(line, &region.end) {
+                    self.region = None;
+                    None
+                } else {
+                    region.language
+                }
+            }
+            None => {
+                self.region = if self.markdown {
+                    self.open_fence(line)
+                } else {
+                    self.open_tag(line)
+                };
+                None
+            }
+        }
+    }
+
+    fn closes(line: &str, end: &End) -> bool {
+        match *end {
+            End::Tag(tag) => line.to_ascii_lowercase().contains(tag),
+            End::Fence { marker, len } => {
+                let trimmed = line.trim();
+                let run = trimmed.chars().take_while(|&c| c == marker).count();
+                run >= len && run == trimmed.chars().count()
+            }
+        }
+    }
+
+    fn open_fence(&self, line: &str) -> Option<Region<'a>> {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        if indent > 3 {
+            return None;
+        }
+        let trimmed = &line[indent..];
+        let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+        let len = trimmed.chars().take_while(|&c| c == marker).count();
+        if len < 3 {
+            return None;
+        }
+
+        let info = trimmed[len..].split_whitespace().next().unwrap_or("");
+        let info = info.trim_start_matches('{').trim_start_matches('.');
+        let info = info.split([',', '}']).next().unwrap_or("");
+        Some(Region {
+            language: self.registry.by_alias(info),
+            end: End::Fence { marker, len },
+        })
+    }
+
+    fn open_tag(&self, line: &str) -> Option<Region<'a>> {
+        let lower = line.to_ascii_lowercase();
+        let (start, end, default) = if let Some(start) = lower.find("<script") {
+            (start, "</script", "JavaScript")
+        } else if let Some(start) = lower.find("<style") {
+            (start, "</style", "CSS")
+        } else {
+            return None;
+        };
+
+        // A block opened and closed on the same line stays with the parent
+        let tag = &lower[start..];
+        if tag.contains(end) {
+            return None;
+        }
+        let tag = tag.split('>').next().unwrap_or(tag);
+
+        let language = match Self::attribute(tag, "lang") {
+            Some(lang) => self.registry.by_alias(lang),
+            None => match Self::attribute(tag, "type") {
+                Some("module" | "text/javascript" | "application/javascript") => Some(default),
+                Some("text/typescript" | "application/typescript") => Some("TypeScript"),
+                Some("text/css") => Some(default),
+                // JSON, import maps, client-side templates and the like
+                Some(_) => None,
+                None => Some(default),
+            },
+        };
+
+        Some(Region {
+            language,
+            end: End::Tag(end),
+        })
+    }
+
+    /// Extracts a quoted attribute value from a lowercased opening tag.
+    fn attribute<'t>(tag: &'t str, name: &str) -> Option<&'t str> {
+        let mut rest = tag;
+        while let Some(pos) = rest.find(name) {
+            let preceded_by_space = rest[..pos].ends_with(char::is_whitespace);
+            rest = &rest[pos + name.len()..];
+            if !preceded_by_space {
+                continue;
+            }
+            let Some(value) = rest.trim_start().strip_prefix('=') else {
+                continue;
+            };
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+            return value[1..].split(quote).next();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The embedded language of each line of `source` in `language`.
+    fn routes(language: &str, source: &str) -> Vec<Option<&'static str>> {
+        let mut splitter = RegionSplitter::for_language(Registry::builtin(), language).unwrap();
+        source.lines().map(|line| splitter.route(line)).collect()
+    }
+
+    #[test]
+    fn script_and_style_blocks_take_their_language() {
+        assert_eq!(
+            routes(
+                "Vue",
+                "<template><p/></template>\n<script lang=\"ts\">\nlet a = 1\n</script>\n<style>\na {}\n</style>"
+            ),
+            [
+                None,
+                None,
+                Some("TypeScript"),
+                None,
+                None,
+                Some("CSS"),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn same_line_and_data_blocks_stay_with_the_parent() {
+        assert_eq!(
+            routes("HTML", "<script src=\"a.js\"></script>\n<p>x</p>"),
+            [None, None]
+        );
+        assert_eq!(
+            routes(
+                "HTML",
+                "<script type=\"application/json\">\n{\"a\": 1}\n</script>\n<p>x</p>"
+            ),
+            [None, None, None, None]
+        );
+        assert_eq!(
+            routes("HTML", "<SCRIPT type='module'>\nimport a\n</Script>"),
+            [None, Some("JavaScript"), None]
+        );
+    }
+
+    #[test]
+    fn fences_close_on_the_same_marker_at_least_as_long() {
+        assert_eq!(
+            routes("Markdown", "````python\nx = 1\n```\n~~~~\n````\ntext"),
+            [
+                None,
+                Some("Python"),
+                Some("Python"),
+                Some("Python"),
+                None,
+                None
+            ]
+        );
+        assert_eq!(
+            routes("Markdown", "~~~ {.json}\n{}\n~~~~~\n```\nplain\n```"),
+            [None, Some("JSON"), None, None, None, None]
+        );
+        // Indented by four spaces, it is a code block rather than a fence
+        assert_eq!(routes("Markdown", "    ```rust\nx"), [None, None]);
+    }
+}
@@ -0,0 +1,325 @@
+use super::error::{CodeStatsError, Result};
+use encoding_rs::{Decoder, Encoding, UTF_8};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use xxhash_rust::xxh3::Xxh3Default;
+
+/// Bytes read from the file at a time.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// How a file is decoded when it has no byte order mark and is not valid
+/// UTF-8.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) enum Fallback {
+    /// Report it as an encoding error
+    #[default]
+    Strict,
+    /// Replace the invalid bytes with U+FFFD
+    Lossy,
+    /// Decode it as a legacy encoding
+    Encoding(&'static Encoding),
+}
+
+impl Fallback {
+    /// `lossy`, or any WHATWG encoding label: `windows-1252`, `latin1`,
+    /// `shift_jis`, `gbk`...
+    pub(crate) fn from_label(label: &str) -> Result<Self> {
+        if label.trim().eq_ignore_ascii_case("lossy") {
+            return Ok(Fallback::Lossy);
+        }
+        Encoding::for_label(label.trim().as_bytes())
+            .map(Fallback::Encoding)
+            .ok_or_else(|| CodeStatsError::Config(format!("unknown fallback_encoding `{}`", label)))
+    }
+}
+
+/// Reads a file as text one line at a time, without holding more than a
+/// line and a read buffer in memory.
+///
+/// A byte order mark selects UTF-8, UTF-16LE or UTF-16BE; without one the
+/// file is read as UTF-8.
+pub struct LineReader {
+    reader: BufReader<Source>,
+    /// Decoder for UTF-16 or a legacy encoding; UTF-8 is read directly
+    decoder: Option<Decoder>,
+    /// Replace malformed input rather than fail
+    lossy: bool,
+    /// Bytes of the line last returned that are still in `reader`'s buffer
+    pending: usize,
+    /// A UTF-8 line spanning several reads
+    raw: Vec<u8>,
+    /// Decoded text: the line last returned, and for a decoder what follows
+    text: String,
+    /// Start of the next line in `text`
+    start: usize,
+    eof: bool,
+}
+
+impl LineReader {
+    /// Opens the file at `path`, failing on the first line that is not
+    /// valid UTF-8 (or valid for the encoding its byte order mark names).
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with(path, Fallback::Strict)
+    }
+
+    /// Opens the file at `path`, handling invalid UTF-8 with `fallback`.
+    ///
+    /// `Fallback::Encoding` decodes the whole file with that encoding, so
+    /// callers first try to read it as UTF-8.
+    pub(crate) fn open_with(path: &Path, fallback: Fallback) -> Result<Self> {
+        Self::open_source(path, fallback, None)
+    }
+
+    /// Opens the file at `path` like [`LineReader::open_with`], hashing its
+    /// bytes as they are read; see [`LineReader::hash`].
+    pub(crate) fn open_hashed(path: &Path, fallback: Fallback) -> Result<Self> {
+        Self::open_source(path, fallback, Some(Xxh3Default::new()))
+    }
+
+    fn open_source(path: &Path, fallback: Fallback, hasher: Option<Xxh3Default>) -> Result<Self> {
+        let source = Source {
+            file: File::open(path)?,
+            hasher,
+        };
+        let mut reader = BufReader::with_capacity(BUFFER_SIZE, source);
+        let (encoding, bom_length) = Encoding::for_bom(reader.fill_buf()?).unwrap_or((UTF_8, 0));
+        reader.consume(bom_length);
+
+        let decoder = match fallback {
+            Fallback::Encoding(legacy) if bom_length == 0 => Some(legacy),
+            _ if encoding != UTF_8 => Some(encoding),
+            _ => None,
+        }
+        .map(Encoding::new_decoder_without_bom_handling);
+        Ok(Self {
+            reader,
+            decoder,
+            lossy: matches!(fallback, Fallback::Lossy),
+            pending: 0,
+            raw: Vec::new(),
+            text: String::new(),
+            start: 0,
+            eof: false,
+        })
+    }
+
+    /// The next line, with its line terminator, or `None` at the end of the
+    /// file.
+    pub fn next_line(&mut self) -> Result<Option<&str>> {
+        if self.decoder.is_some() {
+            self.next_decoded_line()
+        } else {
+            self.next_utf8_line()
+        }
+    }
+
+    /// The xxh3 hash of the whole file once every line has been read, if
+    /// it was opened with [`LineReader::open_hashed`].
+    pub(crate) fn hash(&self) -> Option<u64> {
+        let hasher = self.reader.get_ref().hasher.as_ref()?;
+        Some(hasher.digest())
+    }
+
+    fn next_utf8_line(&mut self) -> Result<Option<&str>> {
+        self.reader.consume(std::mem::take(&mut self.pending));
+
+        // Most lines lie within the buffer and are returned from it as is
+        let buffer = self.reader.fill_buf()?;
+        if let Some(end) = memchr::memchr(b'\n', buffer) {
+            self.pending = end + 1;
+            return self.utf8(Buffered::Reader);
+        }
+
+        self.raw.clear();
+        loop {
+            let buffer = self.reader.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+            match memchr::memchr(b'\n', buffer) {
+                Some(end) => {
+                    self.raw.extend_from_slice(&buffer[..=end]);
+                    self.reader.consume(end + 1);
+                    break;
+                }
+                None => {
+                    let length = buffer.len();
+                    self.raw.extend_from_slice(buffer);
+                    self.reader.consume(length);
+                }
+            }
+        }
+        if self.raw.is_empty() {
+            return Ok(None);
+        }
+        self.utf8(Buffered::Raw)
+    }
+
+    fn utf8(&mut self, from: Buffered) -> Result<Option<&str>> {
+        let bytes = match from {
+            Buffered::Reader => &self.reader.buffer()[..self.pending],
+            Buffered::Raw => &self.raw[..],
+        };
+        match std::str::from_utf8(bytes) {
+            Ok(line) => Ok(Some(line)),
+            Err(_) if self.lossy => {
+                self.text = String::from_utf8_lossy(bytes).into_owned();
+                Ok(Some(&self.text))
+            }
+            Err(_) => Err(CodeStatsError::EncodingError(
+                "not valid UTF-8 (set fallback_encoding to decode it)".to_string(),
+            )),
+        }
+    }
+
+    fn next_decoded_line(&mut self) -> Result<Option<&str>> {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return Ok(None);
+        };
+        let mut searched = self.start;
+        loop {
+            if let Some(end) = memchr::memchr(b'\n', &self.text.as_bytes()[searched..]) {
+                let line = self.start..searched + end + 1;
+                self.start = line.end;
+                return Ok(Some(&self.text[line]));
+            }
+            if self.eof {
+                let line = self.start..self.text.len();
+                self.start = line.end;
+                return Ok((!line.is_empty()).then(|| &self.text[line]));
+            }
+
+            // Keep only the partial line before decoding more
+            self.text.drain(..self.start);
+            self.start = 0;
+            searched = self.text.len();
+
+            let buffer = self.reader.fill_buf()?;
+            self.eof = buffer.is_empty();
+            let needed = decoder
+                .max_utf8_buffer_length(buffer.len())
+                .unwrap_or(BUFFER_SIZE * 3);
+            self.text.reserve(needed);
+            let (_, read, malformed) = decoder.decode_to_string(buffer, &mut self.text, self.eof);
+            self.reader.consume(read);
+            if malformed && !self.lossy {
+                return Err(CodeStatsError::EncodingError(format!(
+                    "not valid {}",
+                    decoder.encoding().name()
+                )));
+            }
+        }
+    }
+}
+
+/// The file being read, hashed on the way when asked to.
+struct Source {
+    file: File,
+    hasher: Option<Xxh3Default>,
+}
+
+impl Read for Source {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read = self.file.read(buffer)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buffer[..read]);
+        }
+        Ok(read)
+    }
+}
+
+/// Where the UTF-8 line being returned is held.
+enum Buffered {
+    Reader,
+    Raw,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// Writes `content` to a file that is removed when dropped.
+    fn temp_file(content: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        file
+    }
+
+    /// Every line of a file holding `content`, read with `fallback`.
+    fn lines_of(content: &[u8], fallback: Fallback) -> Result<Vec<String>> {
+        let file = temp_file(content);
+        let mut reader = LineReader::open_with(file.path(), fallback)?;
+        let mut lines = Vec::new();
+        while let Some(line) = reader.next_line()? {
+            lines.push(line.to_string());
+        }
+        Ok(lines)
+    }
+
+    #[test]
+    fn lines_keep_their_terminators_and_the_last_may_lack_one() {
+        let lines = lines_of(b"a\r\n\nb", Fallback::Strict).unwrap();
+        assert_eq!(lines, ["a\r\n", "\n", "b"]);
+        assert!(lines_of(b"", Fallback::Strict).unwrap().is_empty());
+    }
+
+    #[test]
+    fn utf8_characters_split_across_reads_survive() {
+        // `é` takes the last byte of the first read and the first of the next
+        let mut content = "a".repeat(BUFFER_SIZE - 1);
+        content.push_str("é\nnext\n");
+        let lines = lines_of(content.as_bytes(), Fallback::Strict).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("aé\n"));
+        assert_eq!(lines[1], "next\n");
+    }
+
+    #[test]
+    fn lines_longer_than_the_buffer_are_whole() {
+        let long = "é".repeat(BUFFER_SIZE * 2);
+        let content = format!("short\n{}\n{}", long, long);
+        let lines = lines_of(content.as_bytes(), Fallback::Strict).unwrap();
+        assert_eq!(lines, ["short\n".to_string(), format!("{}\n", long), long]);
+    }
+
+    #[test]
+    fn byte_order_marks_select_the_encoding() {
+        let lines = lines_of(b"\xEF\xBB\xBFa\nb\n", Fallback::Strict).unwrap();
+        assert_eq!(lines, ["a\n", "b\n"]);
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend("x = 1\né\n".encode_utf16().flat_map(u16::to_le_bytes));
+        let lines = lines_of(&utf16, Fallback::Strict).unwrap();
+        assert_eq!(lines, ["x = 1\n", "é\n"]);
+    }
+
+    #[test]
+    fn invalid_utf8_follows_the_fallback() {
+        let content = b"caf\xE9\n";
+        assert!(matches!(
+            lines_of(content, Fallback::Strict),
+            Err(CodeStatsError::EncodingError(_))
+        ));
+        assert_eq!(
+            lines_of(content, Fallback::Lossy).unwrap(),
+            ["caf\u{FFFD}\n"]
+        );
+        let latin1 = Fallback::from_label("latin1").unwrap();
+        assert_eq!(lines_of(content, latin1).unwrap(), ["café\n"]);
+    }
+
+    #[test]
+    fn hash_covers_the_whole_file() {
+        let content = "x\n".repeat(BUFFER_SIZE);
+        let file = temp_file(content.as_bytes());
+        let mut reader = LineReader::open_hashed(file.path(), Fallback::Strict).unwrap();
+        while reader.next_line().unwrap().is_some() {}
+        assert_eq!(
+            reader.hash(),
+            Some(xxhash_rust::xxh3::xxh3_64(content.as_bytes()))
+        );
+    }
+}
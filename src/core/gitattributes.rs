@@ -0,0 +1,365 @@
+use super::error::{CodeStatsError, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The state of an attribute for a path, as described in gitattributes(5).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AttrValue {
+    /// `attr`
+    Set,
+    /// `-attr`
+    Unset,
+    /// `attr=value`
+    Value(String),
+    /// `!attr`, which forgets any earlier setting
+    Unspecified,
+}
+
+type Assignments = Vec<(String, AttrValue)>;
+
+#[derive(Debug)]
+struct Rule {
+    glob: GlobMatcher,
+    attributes: Assignments,
+}
+
+/// Macro attributes defined with `[attr]` lines.
+#[derive(Debug)]
+pub(crate) struct Macros {
+    definitions: HashMap<String, Assignments>,
+}
+
+impl Macros {
+    /// Starts with git's built-in `binary` macro.
+    pub(crate) fn new() -> Self {
+        let binary = ["diff", "merge", "text"]
+            .iter()
+            .map(|attr| (attr.to_string(), AttrValue::Unset))
+            .collect();
+        Self {
+            definitions: HashMap::from([("binary".to_string(), binary)]),
+        }
+    }
+
+    /// Records the `[attr]` lines of a top-level attributes file.
+    fn define(&mut self, content: &str) {
+        for (pattern, rest) in lines(content) {
+            if let Some(name) = pattern.strip_prefix("[attr]") {
+                let expanded = expand(rest, self);
+                self.definitions.insert(name.to_string(), expanded);
+            }
+        }
+    }
+}
+
+/// The rules of one attributes file.
+#[derive(Debug)]
+pub(crate) struct Gitattributes {
+    /// Directory the walked paths are relative to
+    base: PathBuf,
+    /// Path of `base` relative to the directory holding the file, for
+    /// files above the analysed directory
+    prefix: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl Gitattributes {
+    /// Reads the attributes file at `path`, if there is one, with patterns
+    /// relative to `base`.
+    ///
+    /// `[attr]` lines are ignored: only top-level files may define macros,
+    /// as in git.
+    pub(crate) fn load(path: &Path, base: &Path, macros: &Macros) -> Result<Option<Self>> {
+        Ok(read(path)?.map(|content| Self::parse(base, &content, macros)))
+    }
+
+    /// Reads a top-level attributes file, whose `[attr]` lines define
+    /// macros for it and every file loaded after it.
+    pub(crate) fn load_top_level(
+        path: &Path,
+        base: &Path,
+        macros: &mut Macros,
+    ) -> Result<Option<Self>> {
+        let Some(content) = read(path)? else {
+            return Ok(None);
+        };
+        macros.define(&content);
+        Ok(Some(Self::parse(base, &content, macros)))
+    }
+
+    fn parse(base: &Path, content: &str, macros: &Macros) -> Self {
+        let mut rules = Vec::new();
+        for (pattern, rest) in lines(content) {
+            // Macro definitions, negative patterns, which are forbidden, and
+            // directory patterns, which never match the files inside the
+            // directory
+            if pattern.starts_with("[attr]") || pattern.starts_with('!') || pattern.ends_with('/') {
+                continue;
+            }
+            if let Some(glob) = compile(&pattern) {
+                rules.push(Rule {
+                    glob,
+                    attributes: expand(rest, macros),
+                });
+            }
+        }
+
+        Self {
+            base: base.to_path_buf(),
+            prefix: PathBuf::new(),
+            rules,
+        }
+    }
+
+    /// Matches paths as if the file lived `prefix` above `base`.
+    pub(crate) fn with_prefix(mut self, prefix: &Path) -> Self {
+        self.prefix = prefix.to_path_buf();
+        self
+    }
+
+    /// Applies the rules matching `path` to `attributes`; later lines
+    /// override earlier ones.
+    pub(crate) fn apply(&self, path: &Path, attributes: &mut HashMap<String, AttrValue>) {
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return;
+        };
+        let relative = self.prefix.join(relative);
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.glob.is_match(&relative))
+        {
+            for (name, value) in &rule.attributes {
+                attributes.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(CodeStatsError::Io)
+}
+
+/// The pattern and attribute list of each line that is not blank or a
+/// comment.
+fn lines(content: &str) -> impl Iterator<Item = (String, &str)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(split_pattern)
+}
+
+/// Splits a line into its pattern, unquoting `"..."` patterns, and the
+/// attribute list.
+fn split_pattern(line: &str) -> Option<(String, &str)> {
+    let Some(quoted) = line.strip_prefix('"') else {
+        let (pattern, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        return Some((pattern.to_string(), rest));
+    };
+
+    let mut pattern = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((pos, c)) = chars.next() {
+        match c {
+            '"' => return Some((pattern, &quoted[pos + 1..])),
+            '\\' => match chars.next()?.1 {
+                't' => pattern.push('\t'),
+                'n' => pattern.push('\n'),
+                escaped => pattern.push(escaped),
+            },
+            c => pattern.push(c),
+        }
+    }
+    None
+}
+
+/// Parses an attribute list, expanding macros that are set.
+fn expand(list: &str, macros: &Macros) -> Assignments {
+    let mut assignments = Vec::new();
+    for token in list.split_whitespace() {
+        let (name, value) = if let Some(name) = token.strip_prefix('-') {
+            (name, AttrValue::Unset)
+        } else if let Some(name) = token.strip_prefix('!') {
+            (name, AttrValue::Unspecified)
+        } else if let Some((name, value)) = token.split_once('=') {
+            (name, AttrValue::Value(value.to_string()))
+        } else {
+            (token, AttrValue::Set)
+        };
+
+        if value == AttrValue::Set {
+            if let Some(expansion) = macros.definitions.get(name) {
+                assignments.extend(expansion.iter().cloned());
+            }
+        }
+        assignments.push((name.to_string(), value));
+    }
+    assignments
+}
+
+/// Compiles a pattern the way git matches it: without a slash it matches
+/// the file name at any depth, otherwise the path relative to the
+/// `.gitattributes` directory.
+fn compile(pattern: &str) -> Option<GlobMatcher> {
+    let pattern = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .backslash_escape(true)
+        .build()
+        .ok()
+        .map(|glob| glob.compile_matcher())
+}
+
+/// The attributes Linguist reads, each `None` when not specified.
+#[derive(Debug, Default, Hash)]
+pub(crate) struct LinguistAttributes {
+    pub(crate) language: Option<String>,
+    pub(crate) vendored: Option<bool>,
+    pub(crate) generated: Option<bool>,
+    pub(crate) documentation: Option<bool>,
+    pub(crate) detectable: Option<bool>,
+    /// Set by the `binary` macro; `-diff` and `-text` are often put on
+    /// text files such as lock files and say nothing about the content
+    pub(crate) binary: bool,
+}
+
+impl LinguistAttributes {
+    pub(crate) fn from_attributes(attributes: &HashMap<String, AttrValue>) -> Self {
+        let flag = |name: &str| match attributes.get(name)? {
+            AttrValue::Set => Some(true),
+            AttrValue::Unset => Some(false),
+            AttrValue::Value(value) => Some(value != "false"),
+            AttrValue::Unspecified => None,
+        };
+        Self {
+            language: match attributes.get("linguist-language") {
+                Some(AttrValue::Value(language)) => Some(language.clone()),
+                _ => None,
+            },
+            vendored: flag("linguist-vendored"),
+            generated: flag("linguist-generated"),
+            documentation: flag("linguist-documentation"),
+            detectable: flag("linguist-detectable"),
+            binary: attributes.get("binary") == Some(&AttrValue::Set),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Linguist attributes `path` gets from a `.gitattributes` at the
+    /// root holding `content`.
+    fn attributes_of(content: &str, path: &str) -> LinguistAttributes {
+        let mut macros = Macros::new();
+        macros.define(content);
+        let gitattributes = Gitattributes::parse(Path::new("root"), content, &macros);
+        let mut attributes = HashMap::new();
+        gitattributes.apply(&Path::new("root").join(path), &mut attributes);
+        LinguistAttributes::from_attributes(&attributes)
+    }
+
+    #[test]
+    fn only_the_binary_macro_marks_binary() {
+        assert!(attributes_of("*.dat binary", "a.dat").binary);
+        assert!(!attributes_of("app.js -diff", "app.js").binary);
+        assert!(!attributes_of("*.svg -diff -merge", "a.svg").binary);
+    }
+
+    #[test]
+    fn text_attribute_does_not_decide_binary() {
+        assert!(!attributes_of("* -text", "main.rs").binary);
+        assert!(!attributes_of("* text", "main.rs").binary);
+        assert!(!attributes_of("* text=auto", "main.rs").binary);
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_at_any_depth() {
+        let content = "*.gen linguist-generated";
+        assert_eq!(attributes_of(content, "a.gen").generated, Some(true));
+        assert_eq!(
+            attributes_of(content, "deep/dir/a.gen").generated,
+            Some(true)
+        );
+        assert_eq!(attributes_of(content, "a.gen.rs").generated, None);
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored() {
+        let content = "vendor/*.js linguist-vendored\n/docs/** linguist-documentation";
+        assert_eq!(attributes_of(content, "vendor/a.js").vendored, Some(true));
+        assert_eq!(attributes_of(content, "vendor/lib/a.js").vendored, None);
+        assert_eq!(attributes_of(content, "src/vendor/a.js").vendored, None);
+        assert_eq!(
+            attributes_of(content, "docs/a/b.md").documentation,
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn unset_and_unspecified_attributes() {
+        let content = "* linguist-vendored\n*.rs -linguist-vendored\n*.md !linguist-vendored";
+        assert_eq!(attributes_of(content, "a.c").vendored, Some(true));
+        assert_eq!(attributes_of(content, "a.rs").vendored, Some(false));
+        assert_eq!(attributes_of(content, "a.md").vendored, None);
+        assert_eq!(
+            attributes_of("* linguist-generated=false", "a.c").generated,
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn later_lines_take_precedence() {
+        let content = "*.h linguist-language=C\ninclude/*.h linguist-language=C++";
+        assert_eq!(
+            attributes_of(content, "include/a.h").language.as_deref(),
+            Some("C++")
+        );
+        assert_eq!(
+            attributes_of(content, "src/a.h").language.as_deref(),
+            Some("C")
+        );
+    }
+
+    #[test]
+    fn macros_expand_when_set() {
+        let content = "[attr]third-party linguist-vendored -linguist-detectable\n\
+                       lib/** third-party";
+        let attributes = attributes_of(content, "lib/x/y.js");
+        assert_eq!(attributes.vendored, Some(true));
+        assert_eq!(attributes.detectable, Some(false));
+        let unset = "[attr]third-party linguist-vendored\n*.js -third-party";
+        assert_eq!(attributes_of(unset, "a.js").vendored, None);
+    }
+
+    #[test]
+    fn negative_and_directory_patterns_are_skipped() {
+        assert_eq!(
+            attributes_of("!*.js linguist-vendored", "a.js").vendored,
+            None
+        );
+        assert_eq!(
+            attributes_of("lib/ linguist-vendored", "lib/a.js").vendored,
+            None
+        );
+    }
+
+    #[test]
+    fn quoted_patterns_are_unquoted() {
+        let content = "\"my file.txt\" linguist-generated";
+        assert_eq!(attributes_of(content, "my file.txt").generated, Some(true));
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a file the heuristics look at.
+const SAMPLE_SIZE: u64 = 16 * 1024;
+
+/// Whether `ext` is shared by several languages, so the file content has
+/// to be looked at.
+pub(crate) fn is_ambiguous(ext: &str) -> bool {
+    matches!(ext, "h" | "m" | "pl" | "r" | "R" | "mod")
+}
+
+/// Picks the language of a file with an ambiguous extension from its
+/// content; `None` when it gives no answer, leaving [`from_siblings`] as a
+/// tiebreaker.
+pub(crate) fn disambiguate(path: &Path, ext: &str) -> Option<&'static str> {
+    let content = read_sample(path).unwrap_or_default();
+    match ext {
+        "h" => header(&content),
+        "m" => dot_m(&content),
+        "pl" => dot_pl(&content),
+        "r" | "R" => Some(if is_rebol(&content) { "Rebol" } else { "R" }),
+        "mod" => {
+            if path.file_name().is_some_and(|name| name == "go.mod") {
+                return Some("Go");
+            }
+            dot_mod(&content)
+        }
+        _ => None,
+    }
+}
+
+fn read_sample(path: &Path) -> Option<String> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(SAMPLE_SIZE)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn lines(content: &str) -> impl Iterator<Item = &str> {
+    content.lines().map(str::trim)
+}
+
+fn has_objective_c(content: &str) -> bool {
+    lines(content).any(|line| {
+        [
+            "@interface",
+            "@implementation",
+            "@protocol",
+            "@end",
+            "#import",
+        ]
+        .iter()
+        .any(|keyword| line.starts_with(keyword))
+    }) || content.contains("NS_ASSUME_NONNULL")
+}
+
+fn header(content: &str) -> Option<&'static str> {
+    if has_objective_c(content) {
+        return Some("Objective-C");
+    }
+
+    let cpp = lines(content).any(|line| {
+        line.starts_with("namespace ")
+            || line.starts_with("template<")
+            || line.starts_with("template <")
+            || line.starts_with("class ")
+            || line.starts_with("using namespace")
+            || line == "public:"
+            || line == "private:"
+            || line == "protected:"
+            || ["iostream", "string", "vector", "memory", "map"]
+                .iter()
+                .any(|header| line == format!("#include <{}>", header))
+    }) || content.contains("std::");
+    cpp.then_some("C++")
+}
+
+fn dot_m(content: &str) -> Option<&'static str> {
+    if has_objective_c(content) {
+        return Some("Objective-C");
+    }
+
+    let matlab = lines(content).any(|line| {
+        line.starts_with('%')
+            || line.starts_with("function ")
+            || line.starts_with("classdef ")
+            || line == "end"
+    });
+    matlab.then_some("MATLAB")
+}
+
+fn dot_pl(content: &str) -> Option<&'static str> {
+    let perl = lines(content).any(|line| {
+        line.starts_with("use strict")
+            || line.starts_with("use warnings")
+            || line.starts_with("my $")
+            || line.starts_with("sub ")
+            || (line.starts_with("#!") && line.contains("perl"))
+            || (line.starts_with("package ") && line.ends_with(';'))
+    });
+    if perl {
+        return Some("Perl");
+    }
+
+    // Directives (`:- module(...)`) and rules (`head(X) :- body(X).`)
+    let prolog = lines(content).any(|line| {
+        line.starts_with(":-")
+            || line.split_once(":-").is_some_and(|(head, _)| {
+                let head = head.trim_end();
+                head.starts_with(|c: char| c.is_ascii_lowercase())
+                    && head
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || "_(), ".contains(c))
+            })
+    });
+    prolog.then_some("Prolog")
+}
+
+fn is_rebol(content: &str) -> bool {
+    lines(content).any(|line| {
+        line.get(..5)
+            .is_some_and(|word| word.eq_ignore_ascii_case("rebol"))
+            && line[5..].trim_start().starts_with('[')
+    })
+}
+
+fn dot_mod(content: &str) -> Option<&'static str> {
+    // Modula-2 keywords are upper case and module headers end with `;`
+    let modula = lines(content).any(|line| {
+        (line.starts_with("MODULE ")
+            || line.starts_with("DEFINITION MODULE ")
+            || line.starts_with("IMPLEMENTATION MODULE "))
+            && line.ends_with(';')
+    });
+    if modula {
+        return Some("Modula-2");
+    }
+
+    let fortran = lines(content).any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("end module") || line == "implicit none" || line == "contains"
+    });
+    fortran.then_some("Fortran")
+}
+
+/// The extensions of the files in `dir`, for [`from_siblings`].
+pub(crate) fn extensions_in(dir: &Path) -> HashSet<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return HashSet::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            path.extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// Uses the `siblings` extensions of the files next to one with `ext` as a
+/// tiebreaker.
+pub(crate) fn from_siblings(ext: &str, siblings: &HashSet<String>) -> Option<&'static str> {
+    let has = |exts: &[&str]| exts.iter().any(|e| siblings.contains(*e));
+
+    match ext {
+        "h" if has(&["cpp", "cc", "cxx", "hpp", "hxx"]) => Some("C++"),
+        "h" if has(&["m", "mm"]) => Some("Objective-C"),
+        "h" if has(&["c"]) => Some("C"),
+        "m" if has(&["h", "mm"]) => Some("Objective-C"),
+        "m" if has(&["mat", "mlx", "fig"]) => Some("MATLAB"),
+        "pl" if has(&["pm", "t"]) => Some("Perl"),
+        "pl" if has(&["pro", "P"]) => Some("Prolog"),
+        "mod" if has(&["f90", "f95", "f03", "f08", "f", "for"]) => Some("Fortran"),
+        "mod" if has(&["def", "mi"]) => Some("Modula-2"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn siblings(exts: &[&str]) -> HashSet<String> {
+        exts.iter().map(|ext| ext.to_string()).collect()
+    }
+
+    #[test]
+    fn headers_follow_their_sources() {
+        assert_eq!(from_siblings("h", &siblings(&["h", "cpp"])), Some("C++"));
+        assert_eq!(
+            from_siblings("h", &siblings(&["h", "m"])),
+            Some("Objective-C")
+        );
+        assert_eq!(from_siblings("h", &siblings(&["h", "c"])), Some("C"));
+        assert_eq!(from_siblings("h", &siblings(&["h"])), None);
+    }
+
+    #[test]
+    fn content_picks_among_the_languages_sharing_an_extension() {
+        let cases = [
+            (
+                "a.h",
+                "@interface Foo : NSObject\n@end\n",
+                Some("Objective-C"),
+            ),
+            ("a.h", "namespace foo {\nint bar();\n}\n", Some("C++")),
+            ("a.h", "#include <vector>\n", Some("C++")),
+            ("a.h", "int bar(void);\n", None),
+            (
+                "a.m",
+                "#import <Foundation/Foundation.h>\n",
+                Some("Objective-C"),
+            ),
+            ("a.m", "% compute\nx = 1;\n", Some("MATLAB")),
+            ("a.m", "function y = f(x)\n  y = x;\nend\n", Some("MATLAB")),
+            ("a.pl", "use strict;\nmy $x = 1;\n", Some("Perl")),
+            ("a.pl", ":- module(foo, [bar/1]).\n", Some("Prolog")),
+            ("a.pl", "parent(X, Y) :- father(X, Y).\n", Some("Prolog")),
+            ("a.pl", "print 1;\n", None),
+            ("a.r", "REBOL [\n    Title: \"x\"\n]\n", Some("Rebol")),
+            ("a.R", "x <- c(1, 2)\n", Some("R")),
+            ("a.mod", "MODULE Hello;\nEND Hello.\n", Some("Modula-2")),
+            ("a.mod", "DEFINITION MODULE Lists;\n", Some("Modula-2")),
+            ("a.mod", "module foo\nend module foo\n", Some("Fortran")),
+            ("a.mod", "  implicit none\n", Some("Fortran")),
+            ("a.mod", "x\n", None),
+            ("go.mod", "module example.com/foo\n\ngo 1.22\n", Some("Go")),
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content, expected) in cases {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            let ext = path.extension().unwrap().to_str().unwrap();
+            assert_eq!(
+                disambiguate(&path, ext),
+                expected,
+                "{} with {:?}",
+                name,
+                content
+            );
+        }
+    }
+}
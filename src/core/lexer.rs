@@ -0,0 +1,684 @@
+use super::config::MixedLines;
+use super::registry::{LanguageSyntax, StringSyntax};
+use super::stats::LineStats;
+
+/// Classification of a single source line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineKind {
+    Blank,
+    Code,
+    Comment,
+    Doc,
+    /// Code followed or preceded by a comment on the same line
+    Mixed {
+        doc: bool,
+    },
+}
+
+/// What the lexer is inside of when a line ends.
+#[derive(Debug, Clone, Copy)]
+enum State<'a> {
+    Code,
+    BlockComment {
+        open: &'a str,
+        close: &'a str,
+        /// Open comments of this kind, or `None` if the kind does not nest
+        depth: Option<usize>,
+        doc: bool,
+    },
+    String {
+        syntax: &'a StringSyntax,
+        /// Docstrings count as documentation rather than code
+        doc: bool,
+    },
+    /// A raw string, closed by [`LineLexer::raw_close`]
+    RawString,
+}
+
+/// Accumulates [`LineStats`] for one file, line by line.
+pub(crate) struct LineCounter<'a> {
+    lexer: LineLexer<'a>,
+    mixed_lines: MixedLines,
+    stats: LineStats,
+    /// Comment lines that become docs if an exported declaration follows
+    pending_comments: usize,
+}
+
+impl<'a> LineCounter<'a> {
+    pub(crate) fn new(syntax: &'a LanguageSyntax, mixed_lines: MixedLines) -> Self {
+        Self {
+            lexer: LineLexer::new(syntax),
+            mixed_lines,
+            stats: LineStats::default(),
+            pending_comments: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, line: &str) {
+        self.stats.total += 1;
+        let kind = self.lexer.classify(line);
+
+        if kind == LineKind::Comment && self.lexer.syntax.exported_doc {
+            self.pending_comments += 1;
+            return;
+        }
+        if self.pending_comments > 0 {
+            let pending = std::mem::take(&mut self.pending_comments);
+            let declares = matches!(kind, LineKind::Code | LineKind::Mixed { .. })
+                && is_exported_declaration(line);
+            if declares {
+                self.stats.doc += pending;
+            } else {
+                self.stats.comment += pending;
+            }
+        }
+
+        match kind {
+            LineKind::Blank => self.stats.blank += 1,
+            LineKind::Code => self.stats.code += 1,
+            LineKind::Comment => self.stats.comment += 1,
+            LineKind::Doc => self.stats.doc += 1,
+            LineKind::Mixed { doc } => match self.mixed_lines {
+                MixedLines::Code => self.stats.code += 1,
+                MixedLines::Comment => self.count_comment(doc),
+                MixedLines::Both => {
+                    self.stats.code += 1;
+                    self.count_comment(doc);
+                }
+                MixedLines::Separate => self.stats.mixed += 1,
+            },
+        }
+    }
+
+    fn count_comment(&mut self, doc: bool) {
+        if doc {
+            self.stats.doc += 1;
+        } else {
+            self.stats.comment += 1;
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> LineStats {
+        self.stats.comment += self.pending_comments;
+        self.stats
+    }
+}
+
+/// Whether a Go line declares the package or an exported identifier.
+fn is_exported_declaration(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with("package ") {
+        return true;
+    }
+
+    let Some(rest) = ["func ", "type ", "var ", "const "]
+        .iter()
+        .find_map(|keyword| line.strip_prefix(keyword))
+    else {
+        return false;
+    };
+
+    // Skip a method receiver such as `(s *Server)`
+    let rest = rest.trim_start();
+    let name = match rest.strip_prefix('(') {
+        Some(receiver) => receiver.split_once(')').map_or("", |(_, name)| name),
+        None => rest,
+    };
+    name.trim_start().starts_with(|c: char| c.is_uppercase())
+}
+
+/// Whether a Perl line starts a POD block: `=` and a command word at the
+/// start of the line, such as `=pod`, `=head1` or `=over`.
+fn opens_pod(line: &str) -> bool {
+    line.strip_prefix('=')
+        .is_some_and(|command| command.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+/// Per-file lexer that classifies lines one at a time.
+///
+/// It only knows enough about a language to tell comments from string
+/// literals, so markers inside `"..."` or after code are handled correctly.
+pub(crate) struct LineLexer<'a> {
+    syntax: &'a LanguageSyntax,
+    state: State<'a>,
+    /// Bytes that may start a comment, string or character literal; code
+    /// up to the next one is skipped without trying every marker
+    starts: [bool; 256],
+    /// Closing delimiter of the raw string being read, such as `"##`
+    raw_close: String,
+}
+
+impl<'a> LineLexer<'a> {
+    pub(crate) fn new(syntax: &'a LanguageSyntax) -> Self {
+        let mut starts = [false; 256];
+        let markers = syntax
+            .line_comments
+            .iter()
+            .chain(&syntax.doc_line_comments)
+            .chain(
+                syntax
+                    .block_comments
+                    .iter()
+                    .chain(&syntax.nested_comments)
+                    .chain(&syntax.doc_block_comments)
+                    .map(|(open, _)| open),
+            )
+            .chain(syntax.strings.iter().map(|string| &string.open));
+        for marker in markers {
+            if let Some(first) = marker.bytes().next() {
+                starts[usize::from(first)] = true;
+            }
+        }
+        if syntax.raw_strings {
+            starts[usize::from(b'r')] = true;
+            starts[usize::from(b'b')] = true;
+        }
+        if syntax.char_literals {
+            starts[usize::from(b'\'')] = true;
+        }
+
+        Self {
+            syntax,
+            state: State::Code,
+            starts,
+            raw_close: String::new(),
+        }
+    }
+
+    pub(crate) fn classify(&mut self, line: &str) -> LineKind {
+        if line.trim().is_empty() {
+            return LineKind::Blank;
+        }
+
+        if self.syntax.pod && matches!(self.state, State::Code) && opens_pod(line) {
+            self.state = State::BlockComment {
+                open: "=",
+                close: "=cut",
+                depth: None,
+                doc: false,
+            };
+        }
+
+        let mut has_code = false;
+        let mut has_comment = false;
+        let mut has_doc = false;
+        let mut i = 0;
+
+        while i < line.len() {
+            let rest = &line[i..];
+            match self.state {
+                State::BlockComment {
+                    open,
+                    close,
+                    depth,
+                    doc,
+                } => {
+                    if doc {
+                        has_doc = true;
+                    } else {
+                        has_comment = true;
+                    }
+                    let close_pos = rest.find(close);
+                    let open_pos = depth.and_then(|_| rest.find(open));
+                    match (open_pos, close_pos, depth) {
+                        (Some(o), c, Some(d)) if c.is_none_or(|c| o < c) => {
+                            i += o + open.len();
+                            self.state = State::BlockComment {
+                                open,
+                                close,
+                                depth: Some(d + 1),
+                                doc,
+                            };
+                        }
+                        (_, Some(c), Some(d)) if d > 1 => {
+                            i += c + close.len();
+                            self.state = State::BlockComment {
+                                open,
+                                close,
+                                depth: Some(d - 1),
+                                doc,
+                            };
+                        }
+                        (_, Some(c), _) => {
+                            i += c + close.len();
+                            self.state = State::Code;
+                        }
+                        (_, None, _) => break,
+                    }
+                }
+                State::String { syntax, doc } => {
+                    if doc {
+                        has_doc = true;
+                    } else {
+                        has_code = true;
+                    }
+                    match Self::find_string_end(rest, syntax) {
+                        Some(len) => {
+                            i += len;
+                            self.state = State::Code;
+                        }
+                        None => break,
+                    }
+                }
+                State::RawString => {
+                    has_code = true;
+                    match rest.find(self.raw_close.as_str()) {
+                        Some(pos) => {
+                            i += pos + self.raw_close.len();
+                            self.state = State::Code;
+                        }
+                        None => break,
+                    }
+                }
+                State::Code => {
+                    // Markers are ASCII or start with a UTF-8 lead byte, so
+                    // the next start byte is on a character boundary
+                    let skip = rest
+                        .bytes()
+                        .position(|b| self.starts[usize::from(b)])
+                        .unwrap_or(rest.len());
+                    if skip > 0 {
+                        has_code |= rest[..skip].chars().any(|c| !c.is_whitespace());
+                        i += skip;
+                        continue;
+                    }
+
+                    let c = rest.chars().next().unwrap_or(' ');
+                    if c.is_whitespace() {
+                        i += c.len_utf8();
+                        continue;
+                    }
+
+                    if self
+                        .syntax
+                        .doc_line_comments
+                        .iter()
+                        .any(|marker| Self::opens_doc(rest, marker, None))
+                    {
+                        has_doc = true;
+                        break;
+                    }
+
+                    let syntax = self.syntax;
+                    if let Some((open, close)) =
+                        syntax.doc_block_comments.iter().find(|(open, _)| {
+                            let plain_close = syntax
+                                .block_comments
+                                .iter()
+                                .chain(&syntax.nested_comments)
+                                .find(|(plain, _)| open.starts_with(plain.as_str()))
+                                .map(|(_, close)| close.as_str());
+                            Self::opens_doc(rest, open, plain_close)
+                        })
+                    {
+                        // Depth counts the plain opener (`/*`), which also
+                        // opens the comments nested in a doc block (`/**`)
+                        let nested_open = self
+                            .syntax
+                            .nested_comments
+                            .iter()
+                            .find(|(_, nested_close)| nested_close == close)
+                            .map(|(nested_open, _)| nested_open.as_str());
+                        has_doc = true;
+                        i += open.len();
+                        self.state = State::BlockComment {
+                            open: nested_open.unwrap_or(open.as_str()),
+                            close: close.as_str(),
+                            depth: nested_open.map(|_| 1),
+                            doc: true,
+                        };
+                        continue;
+                    }
+
+                    let nested = syntax
+                        .nested_comments
+                        .iter()
+                        .map(|(open, close)| (open.as_str(), close.as_str(), Some(1)));
+                    let flat = syntax
+                        .block_comments
+                        .iter()
+                        .map(|(open, close)| (open.as_str(), close.as_str(), None));
+                    if let Some((open, close, depth)) = nested
+                        .chain(flat)
+                        .find(|(open, _, _)| rest.starts_with(open))
+                    {
+                        has_comment = true;
+                        i += open.len();
+                        self.state = State::BlockComment {
+                            open,
+                            close,
+                            depth,
+                            doc: false,
+                        };
+                        continue;
+                    }
+
+                    if syntax
+                        .line_comments
+                        .iter()
+                        .any(|marker| rest.starts_with(marker.as_str()))
+                        && !syntax
+                            .not_comments
+                            .iter()
+                            .any(|prefix| rest.starts_with(prefix.as_str()))
+                    {
+                        has_comment = true;
+                        break;
+                    }
+
+                    if let Some((len, hashes)) = self.raw_string_start(line, i) {
+                        has_code = true;
+                        i += len;
+                        self.raw_close.clear();
+                        self.raw_close.push('"');
+                        self.raw_close.extend(std::iter::repeat_n('#', hashes));
+                        self.state = State::RawString;
+                        continue;
+                    }
+
+                    if let Some(syntax) = syntax
+                        .strings
+                        .iter()
+                        .find(|s| rest.starts_with(s.open.as_str()))
+                    {
+                        let doc = syntax.docstring && !has_code;
+                        if doc {
+                            has_doc = true;
+                        } else {
+                            has_code = true;
+                        }
+                        i += syntax.open.len();
+                        self.state = State::String { syntax, doc };
+                        continue;
+                    }
+
+                    has_code = true;
+                    if c == '\'' && self.syntax.char_literals {
+                        i += Self::char_literal_len(rest).unwrap_or(1);
+                    } else {
+                        i += c.len_utf8();
+                    }
+                }
+            }
+        }
+
+        // Single-line literals do not carry over, even when left unterminated
+        if let State::String { syntax, .. } = self.state {
+            if !syntax.multiline {
+                self.state = State::Code;
+            }
+        }
+
+        if has_code && (has_doc || has_comment) {
+            LineKind::Mixed { doc: has_doc }
+        } else if has_code {
+            LineKind::Code
+        } else if has_doc {
+            LineKind::Doc
+        } else if has_comment {
+            LineKind::Comment
+        } else {
+            LineKind::Code
+        }
+    }
+
+    /// Whether `rest` starts a documentation comment with `marker`.
+    ///
+    /// Repeating the marker's last character (`////`, `/***`) makes an
+    /// ordinary banner comment, and `/**/` is just an empty block comment.
+    fn opens_doc(rest: &str, marker: &str, plain_close: Option<&str>) -> bool {
+        let Some(after) = rest.strip_prefix(marker) else {
+            return false;
+        };
+        if marker
+            .chars()
+            .next_back()
+            .is_some_and(|c| after.starts_with(c))
+        {
+            return false;
+        }
+        !plain_close.is_some_and(|close| rest[marker.len() - 1..].starts_with(close))
+    }
+
+    /// Returns the byte length up to and including the closing delimiter.
+    fn find_string_end(rest: &str, syntax: &StringSyntax) -> Option<usize> {
+        let mut chars = rest.char_indices();
+        while let Some((pos, c)) = chars.next() {
+            if syntax.escapes && c == '\\' {
+                chars.next();
+            } else if rest[pos..].starts_with(syntax.close.as_str()) {
+                return Some(pos + syntax.close.len());
+            }
+        }
+        None
+    }
+
+    /// Matches `r"`, `r#"`, `br##"` and friends at byte offset `i`,
+    /// returning the opener length and the number of hashes.
+    fn raw_string_start(&self, line: &str, i: usize) -> Option<(usize, usize)> {
+        if !self.syntax.raw_strings {
+            return None;
+        }
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        if line[..i].chars().next_back().is_some_and(is_ident) {
+            return None;
+        }
+
+        let rest = &line[i..];
+        let prefix = if rest.starts_with("br") {
+            2
+        } else if rest.starts_with('r') {
+            1
+        } else {
+            return None;
+        };
+        let hashes = rest[prefix..].chars().take_while(|&c| c == '#').count();
+        rest[prefix + hashes..]
+            .starts_with('"')
+            .then_some((prefix + hashes + 1, hashes))
+    }
+
+    /// Length of a `'x'` or `'\n'` character literal, or `None` when the
+    /// quote starts a lifetime, label or prime instead.
+    fn char_literal_len(rest: &str) -> Option<usize> {
+        let body = &rest[1..];
+        let mut chars = body.char_indices();
+        match chars.next()? {
+            (_, '\\') => body
+                .char_indices()
+                .skip(2)
+                .take(10)
+                .find(|&(_, c)| c == '\'')
+                .map(|(pos, _)| pos + 2),
+            (_, c) => chars
+                .next()
+                .filter(|&(_, next)| next == '\'')
+                .map(|_| c.len_utf8() + 2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::registry::Registry;
+
+    /// The kind of each line of `source` in `language`.
+    fn kinds(language: &str, source: &str) -> Vec<LineKind> {
+        let mut lexer = LineLexer::new(Registry::builtin().syntax(language));
+        source.lines().map(|line| lexer.classify(line)).collect()
+    }
+
+    /// The line counts of `source` in `language` under `mixed_lines`.
+    fn count(language: &str, source: &str, mixed_lines: MixedLines) -> LineStats {
+        let mut counter = LineCounter::new(Registry::builtin().syntax(language), mixed_lines);
+        for line in source.lines() {
+            counter.push(line);
+        }
+        counter.finish()
+    }
+
+    #[test]
+    fn comment_markers_inside_strings_are_code() {
+        assert_eq!(
+            kinds("Rust", r#"let url = "http://x/*";"#),
+            [LineKind::Code]
+        );
+        assert_eq!(
+            kinds("Python", r##"s = "# not a comment""##),
+            [LineKind::Code]
+        );
+        assert_eq!(kinds("Rust", r#"let s = "a \" /* b";"#), [LineKind::Code]);
+        assert_eq!(
+            kinds("Rust", "let s = r#\"a \" /* b\"#; // c"),
+            [LineKind::Mixed { doc: false }]
+        );
+    }
+
+    #[test]
+    fn multiline_strings_carry_over_and_single_line_ones_do_not() {
+        assert_eq!(
+            kinds("Go", "s := `a\n// b\n`"),
+            [LineKind::Code, LineKind::Code, LineKind::Code]
+        );
+        assert_eq!(
+            kinds("Python", "s = \"open\n# comment"),
+            [LineKind::Code, LineKind::Comment]
+        );
+    }
+
+    #[test]
+    fn apostrophes_in_heredocs_and_inline_html_do_not_hide_comments() {
+        assert_eq!(
+            kinds("Shell", "cat <<EOF\ndon't run this\nEOF\n# comment"),
+            [
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Code,
+                LineKind::Comment
+            ]
+        );
+        assert_eq!(
+            kinds("PHP", "<p>Don't panic</p>\n<?php\n// comment"),
+            [LineKind::Code, LineKind::Code, LineKind::Comment]
+        );
+    }
+
+    #[test]
+    fn char_literals_are_told_from_lifetimes() {
+        assert_eq!(
+            kinds("Rust", "let q = '\"'; // quote"),
+            [LineKind::Mixed { doc: false }]
+        );
+        assert_eq!(
+            kinds("Rust", "fn f<'a>(x: &'a str) {} // c"),
+            [LineKind::Mixed { doc: false }]
+        );
+    }
+
+    #[test]
+    fn docstrings_are_documentation() {
+        assert_eq!(
+            kinds(
+                "Python",
+                "def f():\n    \"\"\"Docs\n    more\"\"\"\n    x = \"\"\"s\"\"\""
+            ),
+            [LineKind::Code, LineKind::Doc, LineKind::Doc, LineKind::Code]
+        );
+    }
+
+    #[test]
+    fn nested_comments_need_every_close() {
+        assert_eq!(
+            kinds("Rust", "/* a /* b */ c\nstill */\nfn f() {}"),
+            [LineKind::Comment, LineKind::Comment, LineKind::Code]
+        );
+        assert_eq!(
+            kinds("Haskell", "{- a {- b -} c -} x = 1"),
+            [LineKind::Mixed { doc: false }]
+        );
+        // C block comments do not nest
+        assert_eq!(
+            kinds("C", "/* a /* b */ int x;"),
+            [LineKind::Mixed { doc: false }]
+        );
+    }
+
+    #[test]
+    fn doc_markers_and_banners() {
+        assert_eq!(kinds("Rust", "//! Crate docs"), [LineKind::Doc]);
+        assert_eq!(kinds("Rust", "//// banner"), [LineKind::Comment]);
+        assert_eq!(kinds("C", "/***********/"), [LineKind::Comment]);
+        assert_eq!(kinds("C", "/**/"), [LineKind::Comment]);
+        assert_eq!(kinds("Haskell", "-- | Docs"), [LineKind::Doc]);
+    }
+
+    #[test]
+    fn mixed_lines_follow_the_policy() {
+        let source = "x = 1  # set x\n# alone\ny = 2";
+        let code = count("Python", source, MixedLines::Code);
+        assert_eq!((code.code, code.comment, code.mixed), (2, 1, 0));
+        let comment = count("Python", source, MixedLines::Comment);
+        assert_eq!((comment.code, comment.comment, comment.mixed), (1, 2, 0));
+        let both = count("Python", source, MixedLines::Both);
+        assert_eq!((both.code, both.comment, both.mixed), (2, 2, 0));
+        let separate = count("Python", source, MixedLines::Separate);
+        assert_eq!((separate.code, separate.comment, separate.mixed), (1, 1, 1));
+        assert_eq!(separate.total, 3);
+    }
+
+    #[test]
+    fn go_comments_above_exported_declarations_are_docs() {
+        let source = "// Run runs.\nfunc Run() {}\n// helper\nfunc helper() {}";
+        let stats = count("Go", source, MixedLines::default());
+        assert_eq!((stats.doc, stats.comment, stats.code), (1, 1, 2));
+    }
+
+    #[test]
+    fn doc_blocks_nest_on_the_plain_opener() {
+        assert_eq!(kinds("Rust", "/** a /* b */ c */"), [LineKind::Doc]);
+        assert_eq!(
+            kinds("Rust", "/** a\n/* b */\nc */\nfn main() {}"),
+            [LineKind::Doc, LineKind::Doc, LineKind::Doc, LineKind::Code]
+        );
+    }
+
+    #[test]
+    fn triple_slash_is_documentation_only_where_conventional() {
+        assert_eq!(kinds("Rust", "/// Docs"), [LineKind::Doc]);
+        assert_eq!(kinds("C#", "/// <summary>"), [LineKind::Doc]);
+        assert_eq!(
+            kinds("TypeScript", "/// <reference path=\"a.d.ts\" />"),
+            [LineKind::Comment]
+        );
+        assert_eq!(kinds("JavaScript", "/// note"), [LineKind::Comment]);
+    }
+
+    #[test]
+    fn php_attributes_are_code() {
+        assert_eq!(
+            kinds("PHP", "#[Route('/home')]\n# comment\n$a = 1; # note"),
+            [
+                LineKind::Code,
+                LineKind::Comment,
+                LineKind::Mixed { doc: false }
+            ]
+        );
+    }
+
+    #[test]
+    fn any_pod_command_opens_a_block() {
+        let source = "=head1 NAME\n\nFoo - bar\n=cut\nmy $x = 1;";
+        assert_eq!(
+            kinds("Perl", source),
+            [
+                LineKind::Comment,
+                LineKind::Blank,
+                LineKind::Comment,
+                LineKind::Comment,
+                LineKind::Code
+            ]
+        );
+        assert_eq!(kinds("Perl", "my $y =head;"), [LineKind::Code]);
+    }
+}
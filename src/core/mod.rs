@@ -1,12 +1,25 @@
 mod analyzer;
+mod binary;
+mod cache;
 mod config;
 mod detector;
 mod display;
+mod embedded;
+mod encoding;
 mod error;
+mod gitattributes;
+mod heuristics;
+mod lexer;
+mod registry;
 mod stats;
+mod vendored;
 
 pub use analyzer::ProjectAnalyzer;
-pub use config::Config;
-pub use display::StatsDisplay;
+pub use config::{
+    Category, Config, CustomLanguage, EmbeddedLanguages, FileHandling, MinifiedThresholds,
+    MixedLines,
+};
+pub use display::{FileSort, StatsDisplay};
+pub use encoding::LineReader;
 pub use error::{CodeStatsError, Result};
-pub use stats::{FileStats, LanguageStats, LineStats};
+pub use stats::{DirStats, FileRecord, FileStats, LanguageStats, LineStats};
@@ -0,0 +1,696 @@
+use super::config::{Category, Config, CustomLanguage};
+use super::error::{CodeStatsError, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// Built-in language definitions, see the file itself for the format.
+const DEFINITIONS: &str = include_str!("languages.toml");
+
+/// Name of the catch-all entry used for unknown languages.
+const FALLBACK: &str = "Other";
+
+/// File names only matched with their exact case, since `build` and
+/// `workspace` are as likely to be scripts or directories of anything else.
+const CASE_SENSITIVE_FILENAMES: &[&str] = &["BUILD", "WORKSPACE"];
+
+/// How other languages are embedded in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Embeds {
+    /// `<script>` and `<style>` blocks
+    Html,
+    /// Fenced code blocks
+    Markdown,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A string literal form: its delimiters and how its body is scanned.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct StringSyntax {
+    pub(crate) open: String,
+    pub(crate) close: String,
+    /// A backslash escapes the following character
+    #[serde(default = "default_true")]
+    pub(crate) escapes: bool,
+    /// The literal may continue on the next line
+    #[serde(default)]
+    pub(crate) multiline: bool,
+    /// A literal that starts a line is a docstring and counts as documentation
+    #[serde(default)]
+    pub(crate) docstring: bool,
+}
+
+/// Lexical rules used to classify lines of a given language.
+///
+/// Openers are tried in table order, so longer delimiters sharing a prefix
+/// (`"""` and `"`) must come first.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct LanguageSyntax {
+    pub(crate) line_comments: Vec<String>,
+    pub(crate) block_comments: Vec<(String, String)>,
+    /// Block comments that may contain further comments of the same kind
+    pub(crate) nested_comments: Vec<(String, String)>,
+    pub(crate) doc_line_comments: Vec<String>,
+    /// Documentation blocks; they nest when a nested comment shares their close
+    pub(crate) doc_block_comments: Vec<(String, String)>,
+    /// Comments directly above an exported declaration are documentation (Go)
+    pub(crate) exported_doc: bool,
+    pub(crate) strings: Vec<StringSyntax>,
+    /// `'x'` character literals, told apart from lifetimes and primes
+    pub(crate) char_literals: bool,
+    /// Rust-style `r"..."` / `r#"..."#` raw strings
+    pub(crate) raw_strings: bool,
+    /// Prefixes that start like a line comment but are code, such as PHP 8
+    /// `#[` attributes
+    pub(crate) not_comments: Vec<String>,
+    /// Perl POD: any `=word` line opens a comment block that ends at `=cut`
+    pub(crate) pod: bool,
+}
+
+/// Everything codstts knows about one language.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct LanguageDef {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) category: Category,
+    #[serde(default)]
+    pub(crate) color: Option<String>,
+    #[serde(default)]
+    pub(crate) extensions: Vec<String>,
+    #[serde(default)]
+    pub(crate) filenames: Vec<String>,
+    /// `prefix*` or `*suffix` patterns, matched ignoring case
+    #[serde(default)]
+    pub(crate) filename_patterns: Vec<String>,
+    #[serde(default)]
+    pub(crate) interpreters: Vec<String>,
+    /// Names used in modelines, Markdown fences and `lang` attributes
+    #[serde(default)]
+    pub(crate) aliases: Vec<String>,
+    #[serde(default)]
+    pub(crate) embeds: Option<Embeds>,
+    #[serde(flatten)]
+    pub(crate) syntax: LanguageSyntax,
+}
+
+impl LanguageDef {
+    fn from_custom(name: &str, custom: &CustomLanguage) -> Self {
+        Self {
+            name: name.to_string(),
+            category: Category::Programming,
+            color: custom.color.clone(),
+            extensions: custom.extensions.clone(),
+            filenames: custom.filenames.clone(),
+            filename_patterns: Vec::new(),
+            interpreters: Vec::new(),
+            aliases: Vec::new(),
+            embeds: None,
+            syntax: LanguageSyntax {
+                line_comments: custom.line_comments.clone(),
+                block_comments: custom.block_comments.clone(),
+                strings: custom
+                    .strings
+                    .iter()
+                    .map(|quote| StringSyntax {
+                        open: quote.clone(),
+                        close: quote.clone(),
+                        escapes: true,
+                        multiline: false,
+                        docstring: false,
+                    })
+                    .collect(),
+                ..LanguageSyntax::default()
+            },
+        }
+    }
+}
+
+/// Rejects empty comment and string markers, which would match everywhere
+/// without consuming anything.
+fn validate_custom(name: &str, custom: &CustomLanguage) -> Result<()> {
+    let markers = custom
+        .line_comments
+        .iter()
+        .chain(
+            custom
+                .block_comments
+                .iter()
+                .flat_map(|(open, close)| [open, close]),
+        )
+        .chain(&custom.strings);
+    if markers.into_iter().any(String::is_empty) {
+        return Err(CodeStatsError::Config(format!(
+            "languages.{}: comment and string markers must not be empty",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// The fields of a Linguist `languages.yml` entry that codstts uses.
+#[derive(Debug)]
+struct LinguistLanguage {
+    category: Category,
+    color: Option<String>,
+    extensions: Vec<String>,
+    filenames: Vec<String>,
+    interpreters: Vec<String>,
+    aliases: Vec<String>,
+}
+
+impl LinguistLanguage {
+    /// Reads the languages of a `languages.yml`, by name.
+    fn parse_all(content: &str) -> std::result::Result<BTreeMap<String, Self>, String> {
+        let documents = YamlLoader::load_from_str(content).map_err(|e| e.to_string())?;
+        let Some(languages) = documents.first().and_then(Yaml::as_hash) else {
+            return Err("expected a mapping of language names".to_string());
+        };
+        languages
+            .iter()
+            .map(|(name, entry)| {
+                let name = name
+                    .as_str()
+                    .ok_or_else(|| format!("language name {:?} is not a string", name))?;
+                let language = Self::from_yaml(entry).map_err(|e| format!("{}: {}", name, e))?;
+                Ok((name.to_string(), language))
+            })
+            .collect()
+    }
+
+    fn from_yaml(entry: &Yaml) -> std::result::Result<Self, String> {
+        let list = |key: &str| -> Vec<String> {
+            entry[key].as_vec().map_or_else(Vec::new, |items| {
+                items
+                    .iter()
+                    .filter_map(Yaml::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+        };
+        let category = match entry["type"].as_str() {
+            Some(category) => category
+                .parse()
+                .map_err(|e: CodeStatsError| e.to_string())?,
+            None => Category::default(),
+        };
+        Ok(Self {
+            category,
+            color: entry["color"].as_str().map(str::to_string),
+            extensions: list("extensions"),
+            filenames: list("filenames"),
+            interpreters: list("interpreters"),
+            aliases: list("aliases"),
+        })
+    }
+}
+
+impl LanguageDef {
+    fn from_linguist(name: String, linguist: LinguistLanguage) -> Self {
+        // Linguist extensions keep their dot and may span several
+        // components (`.cmake.in`), which only a file name pattern can match
+        let mut extensions = Vec::new();
+        let mut filename_patterns = Vec::new();
+        for ext in linguist.extensions {
+            let ext = ext.strip_prefix('.').unwrap_or(&ext);
+            if ext.contains('.') {
+                filename_patterns.push(format!("*.{}", ext));
+            } else {
+                extensions.push(ext.to_string());
+            }
+        }
+
+        Self {
+            name,
+            category: linguist.category,
+            color: linguist.color,
+            extensions,
+            filenames: linguist.filenames,
+            filename_patterns,
+            interpreters: linguist.interpreters,
+            aliases: linguist.aliases,
+            embeds: None,
+            syntax: LanguageSyntax::default(),
+        }
+    }
+
+    /// Adds the names `other` is known by, keeping the existing ones.
+    fn absorb(&mut self, other: &LanguageDef) {
+        fn extend(target: &mut Vec<String>, items: &[String]) {
+            for item in items {
+                if !target.contains(item) {
+                    target.push(item.clone());
+                }
+            }
+        }
+        extend(&mut self.extensions, &other.extensions);
+        extend(&mut self.filenames, &other.filenames);
+        extend(&mut self.filename_patterns, &other.filename_patterns);
+        extend(&mut self.interpreters, &other.interpreters);
+        extend(&mut self.aliases, &other.aliases);
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct IgnoreRules {
+    #[serde(default)]
+    filenames: Vec<String>,
+    #[serde(default)]
+    extensions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Definitions {
+    #[serde(default)]
+    ignore: IgnoreRules,
+    #[serde(default, rename = "language")]
+    languages: Vec<LanguageDef>,
+}
+
+/// Lookup tables over the language definitions.
+///
+/// Every subsystem (detection, line counting, display) reads languages
+/// from here, so adding a language only takes a new definition.
+#[derive(Debug, Clone)]
+pub(crate) struct Registry {
+    languages: Vec<LanguageDef>,
+    ignore: IgnoreRules,
+    by_name: HashMap<String, usize>,
+    by_alias: HashMap<String, usize>,
+    by_extension: HashMap<String, usize>,
+    by_filename: HashMap<String, usize>,
+    /// Exact file names in lower case, bar [`CASE_SENSITIVE_FILENAMES`]
+    by_lowercase_filename: HashMap<String, usize>,
+    by_interpreter: HashMap<String, usize>,
+    patterns: Vec<(String, usize)>,
+}
+
+impl Registry {
+    /// The registry built from the embedded definitions.
+    pub(crate) fn builtin() -> &'static Arc<Registry> {
+        static BUILTIN: OnceLock<Arc<Registry>> = OnceLock::new();
+        BUILTIN.get_or_init(|| {
+            Arc::new(Self::parse(DEFINITIONS).expect("embedded language definitions are valid"))
+        })
+    }
+
+    /// The built-in registry extended with the Linguist definitions and the
+    /// custom languages of `config`.
+    pub(crate) fn configured(config: &Config) -> Result<Arc<Registry>> {
+        for (name, custom) in &config.languages {
+            validate_custom(name, custom)?;
+        }
+        if config.linguist_defs.is_none() && config.languages.is_empty() {
+            return Ok(Arc::clone(Self::builtin()));
+        }
+
+        let mut registry = Registry::clone(Self::builtin());
+        if let Some(path) = &config.linguist_defs {
+            registry.add_linguist(path)?;
+        }
+        for (name, custom) in &config.languages {
+            registry.add_custom(LanguageDef::from_custom(name, custom));
+        }
+        Ok(Arc::new(registry))
+    }
+
+    fn parse(definitions: &str) -> Result<Self> {
+        let definitions: Definitions =
+            toml::from_str(definitions).map_err(|e| CodeStatsError::Config(e.to_string()))?;
+
+        let mut registry = Self::empty(definitions.ignore);
+        for language in definitions.languages {
+            registry.add(language);
+        }
+        Ok(registry)
+    }
+
+    fn empty(ignore: IgnoreRules) -> Self {
+        Self {
+            languages: Vec::new(),
+            ignore,
+            by_name: HashMap::new(),
+            by_alias: HashMap::new(),
+            by_extension: HashMap::new(),
+            by_filename: HashMap::new(),
+            by_lowercase_filename: HashMap::new(),
+            by_interpreter: HashMap::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Loads a Linguist `languages.yml`.
+    ///
+    /// Linguist's languages take over the extensions, file names and
+    /// interpreters they list from the built-in ones, as on GitHub. An
+    /// extension several of them list stays with the built-in language of
+    /// the same name, or else goes to the one listing it first. Known
+    /// languages keep their syntax and take Linguist's colour and type.
+    /// Unknown languages borrow the syntax of the built-in language whose
+    /// extension they take (Kotlin from Java, SCSS from CSS), or else the
+    /// generic comment syntax.
+    fn add_linguist(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| CodeStatsError::Config(format!("{}: {}", path.display(), e)))?;
+        let languages = LinguistLanguage::parse_all(&content)
+            .map_err(|e| CodeStatsError::Config(format!("{}: {}", path.display(), e)))?;
+
+        // Linguist lists an extension under every language using it (`.rs`
+        // under Rust and RenderScript), so extensions are settled apart from
+        // the other claims, where the first one wins
+        let mut claims = Self::empty(IgnoreRules::default());
+        let mut extension_claims: BTreeMap<String, Vec<(usize, bool)>> = BTreeMap::new();
+        for (name, linguist) in languages {
+            let primary = linguist
+                .extensions
+                .first()
+                .map(|ext| ext.trim_start_matches('.').to_string());
+            let mut language = LanguageDef::from_linguist(name, linguist);
+            let index = match self.by_name.get(&language.name).copied() {
+                Some(index) => {
+                    let known = &mut self.languages[index];
+                    known.absorb(&language);
+                    known.category = language.category;
+                    if let Some(color) = &language.color {
+                        known.color = Some(color.clone());
+                    }
+                    index
+                }
+                None => {
+                    let borrowed = language
+                        .extensions
+                        .iter()
+                        .find_map(|ext| self.by_extension.get(ext))
+                        .map_or(FALLBACK, |&i| self.languages[i].name.as_str());
+                    language.syntax = self.syntax(borrowed).clone();
+                    self.languages.push(language.clone());
+                    self.languages.len() - 1
+                }
+            };
+            for ext in &language.extensions {
+                let is_primary = primary.as_ref() == Some(ext);
+                extension_claims
+                    .entry(ext.clone())
+                    .or_default()
+                    .push((index, is_primary));
+            }
+            claims.index(index, &language, false);
+        }
+
+        // A language keeps an extension it already owns under the same name;
+        // otherwise the language listing it first wins, as `.php` is Hack's
+        // too but PHP's primary one
+        for (ext, candidates) in extension_claims {
+            let owner = self.by_extension.get(&ext).copied();
+            let (index, _) = candidates
+                .iter()
+                .find(|&&(index, _)| Some(index) == owner)
+                .or_else(|| candidates.iter().find(|&&(_, is_primary)| is_primary))
+                .unwrap_or(&candidates[0]);
+            claims.by_extension.insert(ext, *index);
+        }
+        self.take_over(claims);
+        Ok(())
+    }
+
+    /// Gives the names, extensions and file names `claims` holds to the
+    /// languages it maps them to.
+    fn take_over(&mut self, claims: Registry) {
+        self.by_name.extend(claims.by_name);
+        self.by_alias.extend(claims.by_alias);
+        self.by_extension.extend(claims.by_extension);
+        self.by_filename.extend(claims.by_filename);
+        self.by_lowercase_filename
+            .extend(claims.by_lowercase_filename);
+        self.by_interpreter.extend(claims.by_interpreter);
+        let mut patterns = claims.patterns;
+        patterns.append(&mut self.patterns);
+        self.patterns = patterns;
+    }
+
+    /// Adds a language; earlier definitions keep extensions, file names and
+    /// aliases they already claimed.
+    fn add(&mut self, language: LanguageDef) {
+        let index = self.languages.len();
+        self.index(index, &language, false);
+        self.languages.push(language);
+    }
+
+    /// Adds a language that takes over the names, extensions and file
+    /// names it declares from any existing language.
+    fn add_custom(&mut self, language: LanguageDef) {
+        let index = self.languages.len();
+        self.index(index, &language, true);
+        self.languages.push(language);
+    }
+
+    fn index(&mut self, index: usize, language: &LanguageDef, replace: bool) {
+        let claim = |map: &mut HashMap<String, usize>, key: String| {
+            if replace {
+                map.insert(key, index);
+            } else {
+                map.entry(key).or_insert(index);
+            }
+        };
+
+        claim(&mut self.by_name, language.name.clone());
+        claim(&mut self.by_alias, language.name.to_lowercase());
+        for alias in &language.aliases {
+            claim(&mut self.by_alias, alias.to_lowercase());
+        }
+        for ext in &language.extensions {
+            claim(&mut self.by_extension, ext.clone());
+        }
+        for file_name in &language.filenames {
+            claim(&mut self.by_filename, file_name.clone());
+            if !CASE_SENSITIVE_FILENAMES.contains(&file_name.as_str()) {
+                claim(&mut self.by_lowercase_filename, file_name.to_lowercase());
+            }
+        }
+        for interpreter in &language.interpreters {
+            claim(&mut self.by_interpreter, interpreter.clone());
+        }
+        for pattern in &language.filename_patterns {
+            let pattern = (pattern.to_lowercase(), index);
+            if replace {
+                self.patterns.insert(0, pattern);
+            } else {
+                self.patterns.push(pattern);
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&LanguageDef> {
+        self.by_name.get(name).map(|&i| &self.languages[i])
+    }
+
+    /// The syntax of `name`, or the generic rules for unknown languages.
+    pub(crate) fn syntax(&self, name: &str) -> &LanguageSyntax {
+        let language = self.get(name).or_else(|| self.get(FALLBACK));
+        &language.expect("the fallback language is defined").syntax
+    }
+
+    /// The category of `name`; unknown languages count as programming.
+    pub(crate) fn category(&self, name: &str) -> Category {
+        self.get(name)
+            .map_or(Category::Programming, |language| language.category)
+    }
+
+    pub(crate) fn color(&self, name: &str) -> Option<&str> {
+        self.get(name)?.color.as_deref()
+    }
+
+    /// Resolves a language name or alias, ignoring case.
+    pub(crate) fn by_alias(&self, alias: &str) -> Option<&str> {
+        self.name_at(self.by_alias.get(&alias.to_lowercase()))
+    }
+
+    pub(crate) fn by_extension(&self, ext: &str) -> Option<&str> {
+        let index = self
+            .by_extension
+            .get(ext)
+            .or_else(|| self.by_extension.get(&ext.to_lowercase()));
+        self.name_at(index)
+    }
+
+    /// Matches a file name against the exact names, then against them and
+    /// the patterns ignoring case.
+    ///
+    /// A `prefix*` pattern does not apply when the extension is a known
+    /// one, so `makefile.sh` stays Shell.
+    pub(crate) fn by_filename(&self, file_name: &str) -> Option<&str> {
+        let lowercase = file_name.to_lowercase();
+        if let Some(index) = self
+            .by_filename
+            .get(file_name)
+            .or_else(|| self.by_lowercase_filename.get(&lowercase))
+        {
+            return self.name_at(Some(index));
+        }
+        let known_extension = Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.by_extension(ext).is_some());
+        let index = self
+            .patterns
+            .iter()
+            .find(|(pattern, _)| match pattern.strip_prefix('*') {
+                Some(suffix) => lowercase.ends_with(suffix),
+                None => {
+                    !known_extension
+                        && pattern
+                            .strip_suffix('*')
+                            .is_some_and(|prefix| lowercase.starts_with(prefix))
+                }
+            })
+            .map(|(_, index)| index);
+        self.name_at(index)
+    }
+
+    pub(crate) fn by_interpreter(&self, interpreter: &str) -> Option<&str> {
+        self.name_at(self.by_interpreter.get(interpreter))
+    }
+
+    pub(crate) fn is_ignored_file(&self, file_name: &str) -> bool {
+        self.ignore.filenames.iter().any(|f| f == file_name)
+    }
+
+    pub(crate) fn is_ignored_extension(&self, ext: &str) -> bool {
+        self.ignore.extensions.iter().any(|e| e == ext)
+    }
+
+    fn name_at(&self, index: Option<&usize>) -> Option<&str> {
+        index.map(|&i| self.languages[i].name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(strings: &[&str], block_comments: &[(&str, &str)]) -> CustomLanguage {
+        CustomLanguage {
+            extensions: vec!["foo".to_string()],
+            line_comments: vec!["#".to_string()],
+            block_comments: block_comments
+                .iter()
+                .map(|(open, close)| (open.to_string(), close.to_string()))
+                .collect(),
+            strings: strings.iter().map(|quote| quote.to_string()).collect(),
+            ..CustomLanguage::default()
+        }
+    }
+
+    #[test]
+    fn empty_custom_markers_are_rejected() {
+        let config = Config {
+            languages: BTreeMap::from([("Foo".to_string(), custom(&[""], &[]))]),
+            ..Config::default()
+        };
+        assert!(matches!(
+            Registry::configured(&config),
+            Err(CodeStatsError::Config(_))
+        ));
+        assert!(validate_custom("Foo", &custom(&["\""], &[("/*", "")])).is_err());
+        assert!(validate_custom("Foo", &custom(&["\""], &[("/*", "*/")])).is_ok());
+    }
+
+    #[test]
+    fn exact_file_names_match_ignoring_case_except_bazel_ones() {
+        let registry = Registry::builtin();
+        assert_eq!(registry.by_filename("BUILD"), Some("Starlark"));
+        assert_eq!(registry.by_filename("build"), None);
+        assert_eq!(registry.by_filename("workspace"), None);
+        assert_eq!(registry.by_filename("makefile"), Some("Makefile"));
+        assert_eq!(registry.by_filename("MAKEFILE"), Some("Makefile"));
+        assert_eq!(registry.by_filename("GNUMakefile"), Some("Makefile"));
+        assert_eq!(registry.by_filename("dockerfile"), Some("Dockerfile"));
+        assert_eq!(registry.by_filename("containerfile"), Some("Dockerfile"));
+        assert_eq!(registry.by_filename("jenkinsfile"), Some("Groovy"));
+        assert_eq!(registry.by_filename("vagrantfile"), Some("Ruby"));
+    }
+
+    #[test]
+    fn prefix_patterns_do_not_override_known_extensions() {
+        let registry = Registry::builtin();
+        assert_eq!(registry.by_filename("Dockerfile.dev"), Some("Dockerfile"));
+        assert_eq!(registry.by_filename("makefile.sh"), None);
+        assert_eq!(registry.by_filename("project.mk"), Some("Makefile"));
+    }
+
+    /// The built-in registry extended with the Linguist definitions `yaml`.
+    fn with_linguist(yaml: &str) -> Arc<Registry> {
+        let dir = tempfile::tempdir().unwrap();
+        let defs = dir.path().join("languages.yml");
+        fs::write(&defs, yaml).unwrap();
+        let config = Config {
+            linguist_defs: Some(defs),
+            ..Config::default()
+        };
+        Registry::configured(&config).unwrap()
+    }
+
+    #[test]
+    fn linguist_languages_take_over_builtin_extensions() {
+        let registry = with_linguist(
+            "\
+C:
+  type: programming
+  extensions: [\".c\", \".h\"]
+C++:
+  type: programming
+  extensions: [\".cpp\", \".h\"]
+Kotlin:
+  type: programming
+  extensions: [\".kt\", \".kts\"]
+SCSS:
+  type: markup
+  extensions: [\".scss\"]
+TSX:
+  type: programming
+  extensions: [\".tsx\"]
+",
+        );
+        assert_eq!(registry.by_extension("kt"), Some("Kotlin"));
+        assert_eq!(registry.by_extension("scss"), Some("SCSS"));
+        assert_eq!(registry.by_extension("tsx"), Some("TSX"));
+        assert_eq!(registry.by_extension("h"), Some("C"));
+        assert_eq!(registry.syntax("Kotlin").line_comments, ["//"]);
+        // Known languages keep their own syntax
+        assert!(registry.syntax("Rust").raw_strings);
+    }
+
+    #[test]
+    fn linguist_extension_collisions_follow_ownership_and_primary_extensions() {
+        // `.rs` and `.php` are the built-in Rust's and PHP's; `.qq` is
+        // Quux's primary extension but Alpha's second one
+        let registry = with_linguist(
+            "\
+Alpha:
+  extensions: [\".alpha\", \".qq\"]
+Hack:
+  extensions: [\".hack\", \".hh\", \".php\"]
+PHP:
+  extensions: [\".php\", \".phtml\"]
+Quux:
+  extensions: [\".qq\"]
+RenderScript:
+  extensions: [\".rs\", \".rsh\"]
+Rust:
+  extensions: [\".rs\", \".rs.in\"]
+",
+        );
+        assert_eq!(registry.by_extension("rs"), Some("Rust"));
+        assert_eq!(registry.by_extension("php"), Some("PHP"));
+        assert_eq!(registry.by_extension("rsh"), Some("RenderScript"));
+        assert_eq!(registry.by_extension("hh"), Some("Hack"));
+        assert_eq!(registry.by_extension("qq"), Some("Quux"));
+    }
+}
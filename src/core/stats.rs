@@ -1,23 +1,130 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileStats {
     pub bytes: usize,
     pub lines: LineStats,
+    /// Regions written in other languages, such as `<script>` blocks in a Vue file
+    pub embedded: BTreeMap<String, FileStats>,
 }
 
-#[derive(Debug, Default, Clone)]
+impl FileStats {
+    pub fn add(&mut self, other: &FileStats) {
+        self.bytes += other.bytes;
+        self.lines.add(&other.lines);
+        for (language, stats) in &other.embedded {
+            self.embedded
+                .entry(language.clone())
+                .or_default()
+                .add(stats);
+        }
+    }
+
+    /// Folds the embedded regions into this file's own counts, keeping the
+    /// per-language breakdown in `embedded`.
+    pub fn rolled_up(mut self) -> Self {
+        for stats in self.embedded.values() {
+            self.bytes += stats.bytes;
+            self.lines.add(&stats.lines);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LineStats {
     pub total: usize,
     pub code: usize,
     pub comment: usize,
+    pub doc: usize,
+    pub mixed: usize,
     pub blank: usize,
 }
 
+impl LineStats {
+    pub fn add(&mut self, other: &LineStats) {
+        self.total += other.total;
+        self.code += other.code;
+        self.comment += other.comment;
+        self.doc += other.doc;
+        self.mixed += other.mixed;
+        self.blank += other.blank;
+    }
+}
+
+/// What was counted in one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRecord {
+    /// Path as walked, starting with the analysed directory
+    pub path: PathBuf,
+    pub language: String,
+    /// The file's own lines, with embedded regions in `stats.embedded`
+    pub stats: FileStats,
+    pub vendored: bool,
+    pub generated: bool,
+    pub minified: bool,
+}
+
+/// The project's own files under one directory, and its subdirectories.
+#[derive(Debug, Default, Clone)]
+pub struct DirStats {
+    /// Path relative to the analysed directory, empty for the directory itself
+    pub path: PathBuf,
+    pub files: usize,
+    /// Lines by language, with embedded regions under their own language
+    pub languages: BTreeMap<String, FileStats>,
+    pub children: BTreeMap<String, DirStats>,
+}
+
+impl DirStats {
+    fn add(&mut self, file: &FileRecord) {
+        self.files += 1;
+        let mut own = file.stats.clone();
+        for (language, stats) in std::mem::take(&mut own.embedded) {
+            self.languages.entry(language).or_default().add(&stats);
+        }
+        self.languages
+            .entry(file.language.clone())
+            .or_default()
+            .add(&own);
+    }
+
+    /// Every language of the directory added together.
+    pub fn total(&self) -> FileStats {
+        let mut total = FileStats::default();
+        for stats in self.languages.values() {
+            total.add(stats);
+        }
+        total
+    }
+}
+
 #[derive(Debug)]
 pub struct LanguageStats {
     pub stats: HashMap<String, FileStats>,
     pub total_files: usize,
+    /// Vendored files, by language, when they are reported
+    pub vendored: BTreeMap<String, FileStats>,
+    /// Generated files, by language, when they are reported
+    pub generated: BTreeMap<String, FileStats>,
+    /// Files that looked minified, whether skipped or reported
+    pub minified: Vec<PathBuf>,
+    /// Files left out, by reason: a category such as `data`, or `vendored`
+    pub skipped: BTreeMap<String, usize>,
+    /// Every file that was read, sorted by path; excluded vendored and
+    /// generated files are not read
+    pub files: Vec<FileRecord>,
+    /// Colour of each language, from the definitions the project was
+    /// analysed with
+    pub colors: HashMap<String, String>,
+}
+
+impl Default for LanguageStats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LanguageStats {
@@ -25,18 +132,85 @@ impl LanguageStats {
         Self {
             stats: HashMap::new(),
             total_files: 0,
+            vendored: BTreeMap::new(),
+            generated: BTreeMap::new(),
+            minified: Vec::new(),
+            skipped: BTreeMap::new(),
+            files: Vec::new(),
+            colors: HashMap::new(),
         }
     }
 
     pub fn update(&mut self, language: &str, stats: FileStats) {
-        let entry = self.stats.entry(language.to_string()).or_default();
-        entry.bytes += stats.bytes;
-        entry.lines.total += stats.lines.total;
-        entry.lines.code += stats.lines.code;
-        entry.lines.comment += stats.lines.comment;
-        entry.lines.blank += stats.lines.blank;
+        self.stats
+            .entry(language.to_string())
+            .or_default()
+            .add(&stats);
         self.total_files += 1;
     }
+
+    /// Adds a region embedded in another file without counting a new file.
+    pub fn add_embedded(&mut self, language: &str, stats: FileStats) {
+        self.stats
+            .entry(language.to_string())
+            .or_default()
+            .add(&stats);
+    }
+
+    /// Records a file left out of the statistics.
+    pub fn skip(&mut self, reason: &str) {
+        *self.skipped.entry(reason.to_string()).or_default() += 1;
+    }
+
+    /// Adds the statistics gathered from another part of the project.
+    pub fn merge(&mut self, other: LanguageStats) {
+        for (language, stats) in other.stats {
+            self.stats.entry(language).or_default().add(&stats);
+        }
+        self.total_files += other.total_files;
+        for (language, stats) in other.vendored {
+            self.vendored.entry(language).or_default().add(&stats);
+        }
+        for (language, stats) in other.generated {
+            self.generated.entry(language).or_default().add(&stats);
+        }
+        self.minified.extend(other.minified);
+        for (reason, files) in other.skipped {
+            *self.skipped.entry(reason).or_default() += files;
+        }
+        self.files.extend(other.files);
+        self.colors.extend(other.colors);
+    }
+
+    /// Groups the counted files by directory under `root`, going `depth`
+    /// levels down; files deeper than that count in their ancestor at
+    /// `depth`. Vendored, generated and minified files are left out.
+    pub fn by_dir(&self, root: &Path, depth: Option<usize>) -> DirStats {
+        let mut tree = DirStats::default();
+        let counted = self
+            .files
+            .iter()
+            .filter(|file| !(file.vendored || file.generated || file.minified));
+        for file in counted {
+            let relative = file.path.strip_prefix(root).unwrap_or(&file.path);
+            let mut dir = &mut tree;
+            dir.add(file);
+            let parents = relative.parent().into_iter().flat_map(Path::components);
+            for (level, component) in parents.enumerate() {
+                if depth.is_some_and(|depth| level >= depth) {
+                    break;
+                }
+                let name = component.as_os_str().to_string_lossy().into_owned();
+                let path = dir.path.join(&name);
+                dir = dir.children.entry(name).or_insert_with(|| DirStats {
+                    path,
+                    ..DirStats::default()
+                });
+                dir.add(file);
+            }
+        }
+        tree
+    }
 }
 
 pub trait RoundToDecimals {
@@ -49,3 +223,72 @@ impl RoundToDecimals for f64 {
         (self * factor).round() / factor
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str, language: &str, code: usize) -> FileRecord {
+        FileRecord {
+            path: PathBuf::from(path),
+            language: language.to_string(),
+            stats: FileStats {
+                bytes: code * 10,
+                lines: LineStats {
+                    total: code,
+                    code,
+                    ..LineStats::default()
+                },
+                embedded: BTreeMap::new(),
+            },
+            vendored: false,
+            generated: false,
+            minified: false,
+        }
+    }
+
+    #[test]
+    fn directories_fold_deeper_files_and_leave_set_aside_ones_out() {
+        let mut vue = record("proj/src/App.vue", "Vue", 3);
+        vue.stats
+            .embedded
+            .insert("JavaScript".to_string(), record("", "", 4).stats);
+        let mut vendored = record("proj/vendor/lib.js", "JavaScript", 100);
+        vendored.vendored = true;
+        let mut generated = record("proj/api.pb.go", "Go", 100);
+        generated.generated = true;
+        let mut minified = record("proj/dist/app.min.js", "JavaScript", 100);
+        minified.minified = true;
+
+        let stats = LanguageStats {
+            files: vec![
+                record("proj/main.rs", "Rust", 10),
+                record("proj/src/a/b/deep.rs", "Rust", 5),
+                vue,
+                vendored,
+                generated,
+                minified,
+            ],
+            ..LanguageStats::new()
+        };
+        let tree = stats.by_dir(Path::new("proj"), Some(1));
+        let code =
+            |dir: &DirStats, language: &str| dir.languages.get(language).map(|s| s.lines.code);
+
+        assert_eq!(tree.files, 3);
+        assert_eq!(code(&tree, "Rust"), Some(15));
+        assert_eq!(code(&tree, "Vue"), Some(3));
+        assert_eq!(code(&tree, "JavaScript"), Some(4));
+        assert_eq!(code(&tree, "Go"), None);
+        assert_eq!(tree.children.keys().collect::<Vec<_>>(), ["src"]);
+
+        let src = &tree.children["src"];
+        assert_eq!(src.path, Path::new("src"));
+        assert_eq!(src.files, 2);
+        assert!(src.children.is_empty());
+        assert_eq!(code(src, "Rust"), Some(5));
+        assert_eq!(code(src, "Vue"), Some(3));
+        assert_eq!(code(src, "JavaScript"), Some(4));
+        assert_eq!(src.total().lines.code, 12);
+    }
+}
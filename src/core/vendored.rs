@@ -0,0 +1,111 @@
+use regex::RegexSet;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// How much of a file is searched for a generated-code marker.
+const HEADER_SIZE: u64 = 1024;
+
+/// Third-party code checked into the repository, after Linguist's
+/// `vendor.yml`.
+const VENDORED_PATHS: &[&str] = &[
+    r"(^|/)vendors?/",
+    r"(^|/)third[-_]?party/",
+    r"(^|/)3rd[-_]?party/",
+    r"(^|/)node_modules/",
+    r"(^|/)bower_components/",
+    r"(^|/)jspm_packages/",
+    r"(^|/)Godeps/_workspace/",
+    r"(^|/)Pods/",
+    r"(^|/)Carthage/(Build|Checkouts)/",
+    r"(^|/)\.yarn/(releases|plugins|sdks|unplugged)/",
+    r"(^|/)site-packages/",
+];
+
+/// Files written by tools, after Linguist's `generated.rb`.
+const GENERATED_PATHS: &[&str] = &[
+    r"[.-]min\.(js|css|mjs)$",
+    r"\.(js|css)\.map$",
+    r"\.pb\.(go|cc|h)$",
+    r"\.pb\.gw\.go$",
+    r"_pb2(_grpc)?\.pyi?$",
+    r"_(grpc_)?pb\.(js|d\.ts)$",
+    r"\.pb\.swift$",
+    r"\.generated\.\w+$",
+    r"\.[Dd]esigner\.(cs|vb)$",
+    r"\.g\.(dart|cs|i\.cs)$",
+    r"\.freezed\.dart$",
+    r"_generated\.go$",
+    r"(^|/)zz_generated\.[^/]+\.go$",
+    r"\.egg-info/",
+    r"(^|/)__generated__/",
+];
+
+/// Comments tools put near the top of the files they write, in the forms
+/// Linguist accepts: Go's `Code generated ... DO NOT EDIT.`, `@generated`
+/// and .NET's `<auto-generated>`, each opening its comment.
+const GENERATED_MARKER: &str = r"(?m)^\s*(//|#|/\*|\*|--|;|%|<!--|\(\*)\s*(Code generated .* DO NOT EDIT|@generated\b|<auto-generated\b)";
+
+fn matcher(cell: &'static OnceLock<RegexSet>, patterns: &[&str]) -> &'static RegexSet {
+    cell.get_or_init(|| RegexSet::new(patterns).expect("built-in patterns are valid"))
+}
+
+/// Whether `relative`, a path under the analysed directory, is
+/// third-party code.
+pub(crate) fn is_vendored(relative: &Path) -> bool {
+    static VENDORED: OnceLock<RegexSet> = OnceLock::new();
+    matcher(&VENDORED, VENDORED_PATHS).is_match(&slash_path(relative))
+}
+
+/// Whether `relative` looks generated by its name, or the file at `path`
+/// by a comment in its first kilobyte.
+pub(crate) fn is_generated(path: &Path, relative: &Path) -> bool {
+    static GENERATED: OnceLock<RegexSet> = OnceLock::new();
+    matcher(&GENERATED, GENERATED_PATHS).is_match(&slash_path(relative)) || has_marker(path)
+}
+
+fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn has_marker(path: &Path) -> bool {
+    let mut header = Vec::new();
+    let read = fs::File::open(path)
+        .and_then(|file| file.take(HEADER_SIZE).read_to_end(&mut header))
+        .is_ok();
+    read && is_generated_header(&String::from_utf8_lossy(&header))
+}
+
+fn is_generated_header(header: &str) -> bool {
+    static MARKER: OnceLock<RegexSet> = OnceLock::new();
+    matcher(&MARKER, &[GENERATED_MARKER]).is_match(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_tool_markers_mark_a_file_generated() {
+        for header in [
+            "// Code generated by protoc-gen-go. DO NOT EDIT.",
+            "package main\n// Code generated by stringer; DO NOT EDIT.",
+            "/**\n * @generated SignedSource<<abc>>\n */",
+            "# @generated by pants",
+            "// <auto-generated>\n//     This code was generated by a tool.",
+            "<!-- <auto-generated/> -->",
+        ] {
+            assert!(is_generated_header(header), "{}", header);
+        }
+        for header in [
+            "# IDs are automatically generated by the database",
+            "// Auto-generated by hand, then edited",
+            "* automatically generated docs live elsewhere",
+            "// Do not edit the Code generated section below",
+            "let generated = \"@generated\";",
+        ] {
+            assert!(!is_generated_header(header), "{}", header);
+        }
+    }
+}
@@ -57,6 +57,7 @@
 //! 2. Detailed mode: Provides comprehensive statistics including:
 //!    - Total lines of code
 //!    - Comment lines
+//!    - Documentation comment lines
 //!    - Blank lines
 //!    - File counts
 //!    - Byte sizes
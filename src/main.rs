@@ -1,6 +1,7 @@
 use clap::Parser;
-use codstts::core::{Config, ProjectAnalyzer, StatsDisplay};
+use codstts::core::{Category, Config, FileSort, ProjectAnalyzer, StatsDisplay};
 use colored::*;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(
@@ -25,6 +26,55 @@ struct Cli {
     #[arg(short, long)]
     config: Option<String>,
 
+    /// Load languages from a Linguist languages.yml
+    #[arg(long = "linguist-defs", value_name = "PATH")]
+    linguist_defs: Option<PathBuf>,
+
+    /// Also count languages of these categories (data, prose, markup, programming)
+    #[arg(
+        long = "include-categories",
+        value_name = "CATEGORIES",
+        value_delimiter = ','
+    )]
+    include_categories: Vec<Category>,
+
+    /// Decode files that are not UTF-8 with this encoding (`windows-1252`, `shift_jis`, ... or `lossy`)
+    #[arg(long = "fallback-encoding", value_name = "ENCODING")]
+    fallback_encoding: Option<String>,
+
+    /// Reuse the results of unchanged files from `.codstts-cache/`
+    #[arg(long = "cache")]
+    cache: bool,
+
+    /// Number of threads to analyse files with (defaults to one per CPU)
+    #[arg(short = 'j', long = "threads", value_name = "N")]
+    threads: Option<usize>,
+
+    /// List every file with its line counts instead of the language summary
+    #[arg(long = "files", conflicts_with_all = ["simple", "detail", "by_dir"])]
+    files: bool,
+
+    /// Column to sort the file list by (path, language, bytes, lines, code, comments, blank, comment-ratio)
+    #[arg(
+        long = "sort",
+        value_name = "KEY",
+        default_value = "lines",
+        requires = "files"
+    )]
+    sort: FileSort,
+
+    /// Reverse the order of the file list
+    #[arg(long = "reverse", requires = "files")]
+    reverse: bool,
+
+    /// Show a tree of directories with their line counts and languages
+    #[arg(long = "by-dir", conflicts_with_all = ["simple", "detail"])]
+    by_dir: bool,
+
+    /// How many directory levels the tree goes down (all by default)
+    #[arg(long = "depth", value_name = "N", requires = "by_dir")]
+    depth: Option<usize>,
+
     /// Show debug information
     #[arg(short, long)]
     verbose: bool,
@@ -33,19 +83,45 @@ struct Cli {
 fn main() {
     let cli = Cli::parse();
 
-    let config = Config::load().unwrap_or_default();
+    let mut config = Config::load().unwrap_or_default();
+    for category in cli.include_categories {
+        if !config.categories.contains(&category) {
+            config.categories.push(category);
+        }
+    }
+    if cli.linguist_defs.is_some() {
+        config.linguist_defs = cli.linguist_defs;
+    }
+    if cli.cache {
+        config.cache = true;
+    }
+    if cli.fallback_encoding.is_some() {
+        config.fallback_encoding = cli.fallback_encoding;
+    }
 
     let mut analyzer = ProjectAnalyzer::new();
     analyzer.set_config(config);
+    if let Some(threads) = cli.threads {
+        analyzer.set_threads(threads);
+    }
     match analyzer.analyze_project(&cli.path) {
         Ok((stats, other_files)) => {
-            if cli.simple || (!cli.simple && !cli.detail) {
+            if cli.files {
+                StatsDisplay::print_files(&stats, cli.sort, cli.reverse);
+                return;
+            }
+            if cli.by_dir {
+                let tree = stats.by_dir(Path::new(&cli.path), cli.depth);
+                StatsDisplay::print_tree(&stats, &tree, &cli.path);
+                return;
+            }
+            if cli.simple || !cli.detail {
                 StatsDisplay::print_simple_view(&stats);
             } else {
                 StatsDisplay::print_detailed_view(&stats);
             }
 
-            if !other_files.is_empty() && (cli.detail || (!cli.simple && !cli.detail)) {
+            if !other_files.is_empty() && !cli.simple {
                 println!("\n{}", "Unrecognized files:".yellow().bold());
                 for file in other_files {
                     if let Some(file_name) = file.file_name().and_then(|n| n.to_str()) {